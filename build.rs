@@ -0,0 +1,4 @@
+fn main() {
+    // Rebuild when migrations change so sqlx::migrate! embeds the latest set
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -1,18 +1,18 @@
 -- 1) มิติระบบ Azure
-CREATE TABLE subscription (
+CREATE TABLE IF NOT EXISTS subscription (
                               id           BIGSERIAL PRIMARY KEY,
                               name         TEXT NOT NULL,
                               tenant_id    TEXT
 );
 
-CREATE TABLE resource_group (
+CREATE TABLE IF NOT EXISTS resource_group (
                                 id              BIGSERIAL PRIMARY KEY,
                                 name            TEXT NOT NULL,
                                 subscription_id BIGINT NOT NULL REFERENCES subscription(id)
 );
 
 -- 2) แอปพลิเคชัน/บริการ (มาจาก Tags เช่น AppID/AppName)
-CREATE TABLE application (
+CREATE TABLE IF NOT EXISTS application (
                              id        BIGSERIAL PRIMARY KEY,
                              code      TEXT UNIQUE,     -- AppID เช่น 'AP2411'
                              name      TEXT,            -- AppName เช่น 'UDP'
@@ -21,7 +21,7 @@ CREATE TABLE application (
 );
 
 -- 3) Resource หลัก
-CREATE TABLE resource (
+CREATE TABLE IF NOT EXISTS resource (
                           id                BIGSERIAL PRIMARY KEY,
                           azure_id          TEXT UNIQUE,      -- ARM ID (resourceId) ถ้ามี
                           name              TEXT NOT NULL,    -- เช่น '001e8270207...'
@@ -40,7 +40,7 @@ CREATE TABLE resource (
 );
 
 -- 4) Tag แบบ EAV (เหมาะกับ query ‘ค้นตาม TAG’ แบบ join)
-CREATE TABLE resource_tag (
+CREATE TABLE IF NOT EXISTS resource_tag (
                               resource_id BIGINT REFERENCES resource(id) ON DELETE CASCADE,
                               key         TEXT NOT NULL,
                               value       TEXT,
@@ -48,7 +48,7 @@ CREATE TABLE resource_tag (
 );
 
 -- 5) Mapping Resource↔Application
-CREATE TABLE resource_application_map (
+CREATE TABLE IF NOT EXISTS resource_application_map (
                                           resource_id    BIGINT REFERENCES resource(id) ON DELETE CASCADE,
                                           application_id BIGINT REFERENCES application(id) ON DELETE CASCADE,
                                           relation_type  TEXT DEFAULT 'uses',  -- 'uses'/'owns'/'managed-by' (ตามนโยบายคุณ)
@@ -56,10 +56,10 @@ CREATE TABLE resource_application_map (
 );
 
 -- 6) Indexes ที่ควรมี
-CREATE INDEX idx_resource_type          ON resource(type);
-CREATE INDEX idx_resource_location      ON resource(location);
-CREATE INDEX idx_resource_vendor        ON resource(vendor);
-CREATE INDEX idx_resource_environment   ON resource(environment);
-CREATE INDEX idx_resource_tags_gin      ON resource USING GIN (tags_json jsonb_path_ops);
-CREATE INDEX idx_resource_tag_key       ON resource_tag(key);
-CREATE INDEX idx_resource_tag_key_val   ON resource_tag(key, value);
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS idx_resource_type          ON resource(type);
+CREATE INDEX IF NOT EXISTS idx_resource_location      ON resource(location);
+CREATE INDEX IF NOT EXISTS idx_resource_vendor        ON resource(vendor);
+CREATE INDEX IF NOT EXISTS idx_resource_environment   ON resource(environment);
+CREATE INDEX IF NOT EXISTS idx_resource_tags_gin      ON resource USING GIN (tags_json jsonb_path_ops);
+CREATE INDEX IF NOT EXISTS idx_resource_tag_key       ON resource_tag(key);
+CREATE INDEX IF NOT EXISTS idx_resource_tag_key_val   ON resource_tag(key, value);
\ No newline at end of file
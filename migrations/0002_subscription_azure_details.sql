@@ -0,0 +1,2 @@
+-- GUID ของ subscription จาก Azure (คอลัมน์ subscriptionId ใน CSV)
+ALTER TABLE subscription ADD COLUMN IF NOT EXISTS azure_subscription_id TEXT UNIQUE;  -- GUID เช่น '00000000-0000-0000-0000-000000000000'
@@ -0,0 +1,9 @@
+-- ความสัมพันธ์ parent/child ระหว่าง resource (สกัดจาก ARM ID เช่น subnet → VNet, extension → VM)
+CREATE TABLE IF NOT EXISTS resource_relationship (
+                                       parent_id     BIGINT NOT NULL REFERENCES resource(id) ON DELETE CASCADE,
+                                       child_id      BIGINT NOT NULL REFERENCES resource(id) ON DELETE CASCADE,
+                                       relation_type TEXT NOT NULL DEFAULT 'parent',
+                                       PRIMARY KEY (parent_id, child_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_resource_relationship_child ON resource_relationship(child_id);
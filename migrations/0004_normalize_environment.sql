@@ -0,0 +1,24 @@
+-- ปรับค่า environment ให้เป็นรูปแบบมาตรฐาน (DEV/TEST/UAT/PRD/UNKNOWN) ให้ตรงกับ Environment ใน importer
+UPDATE resource
+SET environment = CASE lower(trim(environment))
+                      WHEN 'dev'         THEN 'DEV'
+                      WHEN 'develop'     THEN 'DEV'
+                      WHEN 'development' THEN 'DEV'
+                      WHEN 'test'        THEN 'TEST'
+                      WHEN 'tst'         THEN 'TEST'
+                      WHEN 'testing'     THEN 'TEST'
+                      WHEN 'qa'          THEN 'TEST'
+                      WHEN 'sit'         THEN 'TEST'
+                      WHEN 'uat'         THEN 'UAT'
+                      WHEN 'staging'     THEN 'UAT'
+                      WHEN 'stage'       THEN 'UAT'
+                      WHEN 'stg'         THEN 'UAT'
+                      WHEN 'preprod'     THEN 'UAT'
+                      WHEN 'pre-prod'    THEN 'UAT'
+                      WHEN 'prd'         THEN 'PRD'
+                      WHEN 'prod'        THEN 'PRD'
+                      WHEN 'production'  THEN 'PRD'
+                      WHEN 'live'        THEN 'PRD'
+                      ELSE 'UNKNOWN'
+    END
+WHERE environment IS NOT NULL;
@@ -0,0 +1,37 @@
+-- รวม application ที่ code ซ้ำกันต่างแค่ตัวพิมพ์/ช่องว่าง (เช่น 'APP01' กับ 'app01') ให้เหลือรายการเดียว
+CREATE TEMP TABLE application_merge ON COMMIT DROP AS
+SELECT a.id AS duplicate_id, k.keep_id
+FROM application a
+         JOIN (SELECT UPPER(TRIM(code)) AS norm_code, MIN(id) AS keep_id
+               FROM application
+               WHERE code IS NOT NULL
+               GROUP BY UPPER(TRIM(code))) k ON UPPER(TRIM(a.code)) = k.norm_code
+WHERE a.id <> k.keep_id;
+
+-- ย้าย mapping ของรายการซ้ำไปยังรายการที่เก็บไว้
+INSERT INTO resource_application_map (resource_id, application_id, relation_type)
+SELECT ram.resource_id, m.keep_id, ram.relation_type
+FROM resource_application_map ram
+         JOIN application_merge m ON m.duplicate_id = ram.application_id
+ON CONFLICT DO NOTHING;
+
+-- เติมข้อมูลที่รายการที่เก็บไว้ยังไม่มี จากรายการซ้ำ
+UPDATE application keep
+SET name        = COALESCE(keep.name, d.name),
+    owner_team  = COALESCE(keep.owner_team, d.owner_team),
+    owner_email = COALESCE(keep.owner_email, d.owner_email)
+FROM (SELECT DISTINCT ON (m.keep_id) m.keep_id, a.name, a.owner_team, a.owner_email
+      FROM application_merge m
+               JOIN application a ON a.id = m.duplicate_id
+      ORDER BY m.keep_id, a.id) d
+WHERE keep.id = d.keep_id;
+
+-- ลบรายการซ้ำ (mapping เดิมถูกลบตาม ON DELETE CASCADE)
+DELETE FROM application a
+    USING application_merge m
+WHERE a.id = m.duplicate_id;
+
+UPDATE application SET code = UPPER(TRIM(code)) WHERE code <> UPPER(TRIM(code));
+
+-- กันไม่ให้เกิดซ้ำอีก
+CREATE UNIQUE INDEX IF NOT EXISTS application_code_upper_key ON application (UPPER(code));
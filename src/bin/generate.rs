@@ -0,0 +1,235 @@
+use anyhow::Result;
+use std::env;
+use std::io;
+
+const USAGE: &str = "Usage: generate [--subscriptions N] [--resource-groups N] [--applications N] [--resources N] [--seed N] [--output PATH]";
+
+// Display type, ARM type, kind, and name prefix for the resources we generate
+const RESOURCE_TYPES: &[(&str, &str, &str, &str)] = &[
+    ("Virtual machine", "Microsoft.Compute/virtualMachines", "", "vm"),
+    ("Disk", "Microsoft.Compute/disks", "", "disk"),
+    ("Network interface", "Microsoft.Network/networkInterfaces", "", "nic"),
+    ("Virtual network", "Microsoft.Network/virtualNetworks", "", "vnet"),
+    ("Public IP address", "Microsoft.Network/publicIPAddresses", "", "pip"),
+    ("Storage account", "Microsoft.Storage/storageAccounts", "StorageV2", "st"),
+    ("App Service", "Microsoft.Web/sites", "app", "app"),
+    ("Function App", "Microsoft.Web/sites", "functionapp", "func"),
+    ("Key vault", "Microsoft.KeyVault/vaults", "", "kv"),
+    ("Azure Databricks Service", "Microsoft.Databricks/workspaces", "", "dbw"),
+];
+
+const LOCATIONS: &[&str] = &["southeastasia", "eastasia", "westeurope", "eastus"];
+
+// Includes the spellings seen in real tags so the importer's normalization gets exercised
+const ENVIRONMENTS: &[&str] = &["PRD", "prd", "Production", "UAT", "uat", "DEV", "dev", "TEST", "SIT"];
+
+const VENDORS: &[&str] = &["Microsoft", "Databricks", "Accenture", "In-house"];
+
+const PROVISIONERS: &[&str] = &["Terraform", "Bicep", "Portal"];
+
+#[derive(Debug)]
+struct GeneratorConfig {
+    subscriptions: usize,
+    resource_groups: usize,
+    applications: usize,
+    resources: usize,
+    seed: u64,
+    output: Option<String>,
+}
+
+impl GeneratorConfig {
+    fn from_args() -> Result<Self> {
+        let mut config = Self {
+            subscriptions: 3,
+            resource_groups: 20,
+            applications: 10,
+            resources: 1000,
+            seed: 42,
+            output: None,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(flag) = args.next() {
+            if flag == "--help" || flag == "-h" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            let value = args.next()
+                .ok_or_else(|| anyhow::anyhow!("Missing value for {}\n{}", flag, USAGE))?;
+            let count = || value.parse::<usize>()
+                .map_err(|e| anyhow::anyhow!("Invalid value for {}: '{}' ({})", flag, value, e));
+            match flag.as_str() {
+                "--subscriptions" => config.subscriptions = count()?,
+                "--resource-groups" => config.resource_groups = count()?,
+                "--applications" => config.applications = count()?,
+                "--resources" => config.resources = count()?,
+                "--seed" => config.seed = value.parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid value for --seed: '{}' ({})", value, e))?,
+                "--output" => config.output = Some(value),
+                _ => return Err(anyhow::anyhow!("Unknown argument: {}\n{}", flag, USAGE)),
+            }
+        }
+
+        if config.subscriptions == 0 || config.resource_groups == 0 || config.applications == 0 {
+            return Err(anyhow::anyhow!("--subscriptions, --resource-groups, and --applications must be at least 1"));
+        }
+        Ok(config)
+    }
+}
+
+// Small deterministic PRNG (xorshift64*) so the same seed always yields the same file
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn guid(&mut self) -> String {
+        let hi = self.next_u64();
+        let lo = self.next_u64();
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            hi >> 32,
+            (hi >> 16) & 0xffff,
+            hi & 0xffff,
+            lo >> 48,
+            lo & 0xffff_ffff_ffff
+        )
+    }
+}
+
+struct Subscription {
+    name: String,
+    guid: String,
+}
+
+struct ResourceGroup {
+    name: String,
+    subscription: usize,
+    location: &'static str,
+}
+
+struct Application {
+    code: String,
+    name: String,
+    owner: String,
+}
+
+fn main() -> Result<()> {
+    pretty_env_logger::init();
+    let config = GeneratorConfig::from_args()?;
+    log::debug!("Generator config: {:?}", config);
+
+    let mut rng = Rng::new(config.seed);
+
+    let subscriptions: Vec<Subscription> = (1..=config.subscriptions)
+        .map(|i| Subscription {
+            name: format!("sub-techstock-{:02}", i),
+            guid: rng.guid(),
+        })
+        .collect();
+
+    let applications: Vec<Application> = (1..=config.applications)
+        .map(|i| Application {
+            code: format!("AP{:04}", 1000 + i),
+            name: format!("App {:02}", i),
+            owner: format!("owner{:02}@example.com", i),
+        })
+        .collect();
+
+    let resource_groups: Vec<ResourceGroup> = (1..=config.resource_groups)
+        .map(|i| ResourceGroup {
+            name: format!("rg-workload-{:03}", i),
+            subscription: rng.below(subscriptions.len()),
+            location: LOCATIONS[rng.below(LOCATIONS.len())],
+        })
+        .collect();
+
+    let writer: Box<dyn io::Write> = match &config.output {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "Name", "Type", "kind", "Location", "Subscription", "Resource group",
+        "Tags", "extendedLocation", "subscriptionId", "id",
+    ])?;
+
+    for i in 1..=config.resources {
+        let group = &resource_groups[rng.below(resource_groups.len())];
+        let subscription = &subscriptions[group.subscription];
+        let &(display_type, arm_type, kind, prefix) = rng.pick(RESOURCE_TYPES);
+
+        // The row number keeps names unique; storage account names are lowercase alphanumerics only
+        let name = if prefix == "st" {
+            format!("st{}{:06}", group.name.replace('-', ""), i)
+        } else {
+            format!("{}-{}-{:06}", prefix, group.name.trim_start_matches("rg-"), i)
+        };
+
+        // Most resources follow the resource group's region, a few live elsewhere
+        let location = if rng.chance(90) { group.location } else { *rng.pick(LOCATIONS) };
+
+        // Leave some resources untagged, as in real inventories
+        let tags = if rng.chance(85) {
+            let app = rng.pick(&applications);
+            let mut tags = serde_json::json!({
+                "AppID": app.code,
+                "AppName": app.name,
+                "Environment": rng.pick(ENVIRONMENTS),
+                "AdminName": app.owner,
+            });
+            if rng.chance(60) {
+                tags["Vendor"] = serde_json::json!(rng.pick(VENDORS));
+            }
+            if rng.chance(70) {
+                tags["Provisioner"] = serde_json::json!(rng.pick(PROVISIONERS));
+            }
+            tags.to_string()
+        } else {
+            "null".to_string()
+        };
+
+        let azure_id = format!(
+            "/subscriptions/{}/resourceGroups/{}/providers/{}/{}",
+            subscription.guid, group.name, arm_type, name
+        );
+        writer.write_record([
+            name.as_str(),
+            display_type,
+            kind,
+            location,
+            subscription.name.as_str(),
+            group.name.as_str(),
+            tags.as_str(),
+            "null",
+            subscription.guid.as_str(),
+            azure_id.as_str(),
+        ])?;
+    }
+    writer.flush()?;
+
+    log::info!("Generated {} resources across {} subscriptions, {} resource groups, and {} applications",
+        config.resources, subscriptions.len(), resource_groups.len(), applications.len());
+    Ok(())
+}
@@ -2,10 +2,394 @@ use anyhow::Result;
 use csv::ReaderBuilder;
 use serde::Deserialize;
 use serde_json::Value;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use std::env;
+use std::future::Future;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Retry settings for transient database errors
+const DB_MAX_RETRIES: u32 = 3;
+const DB_RETRY_BASE_DELAY_MS: u64 = 100;
+
+static DB_RETRY_COUNT: AtomicU64 = AtomicU64::new(0);
+
+// Azure Key Vault access via managed identity
+const KEY_VAULT_RESOURCE: &str = "https://vault.azure.net";
+const KEY_VAULT_API_VERSION: &str = "7.4";
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+// Upper bound for the startup connection backoff
+const MAX_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Queries slower than this are logged with their SQL (override with SLOW_QUERY_THRESHOLD_MS)
+const DEFAULT_SLOW_QUERY_THRESHOLD_MS: u64 = 500;
+
+static SLOW_QUERY_THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_QUERY_THRESHOLD_MS);
+static SLOW_QUERY_COUNT: AtomicU64 = AtomicU64::new(0);
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+const DEFAULT_CONFIG_PATH: &str = "techstock.toml";
+const DEFAULT_CSV_PATH: &str = "datasets/AzureResourceGraphFormattedResults-Query.csv";
+
+/// Settings layered from defaults, then `techstock.toml` (or `TECHSTOCK_CONFIG`), then
+/// environment variables.
+#[derive(Debug, Clone)]
+struct Config {
+    file_path: Option<String>,
+    database: DatabaseConfig,
+    csv_path: String,
+    run_migrations: bool,
+    schema_check: SchemaCheck,
+    slow_query_threshold_ms: u64,
+    log_level: Option<String>,
+    log_format: LogFormat,
+    key_vault: Option<KeyVaultConfig>,
+    notifications: Option<NotificationConfig>,
+}
+
+impl Config {
+    fn load() -> Result<Self> {
+        let source = ConfigSource::load()?;
+        Ok(Self {
+            file_path: source.path.clone(),
+            database: DatabaseConfig::from_source(&source)?,
+            csv_path: source.get("CSV_PATH", "import.csv_path")?
+                .unwrap_or_else(|| DEFAULT_CSV_PATH.to_string()),
+            run_migrations: source.get_bool("RUN_MIGRATIONS", "database.run_migrations")?
+                .unwrap_or(false),
+            schema_check: source.get("SCHEMA_CHECK", "database.schema_check")?
+                .unwrap_or(SchemaCheck::Strict),
+            slow_query_threshold_ms: source.get("SLOW_QUERY_THRESHOLD_MS", "database.slow_query_threshold_ms")?
+                .unwrap_or(DEFAULT_SLOW_QUERY_THRESHOLD_MS),
+            log_level: source.get("RUST_LOG", "logging.level")?,
+            log_format: source.get("LOG_FORMAT", "logging.format")?
+                .unwrap_or(LogFormat::Text),
+            key_vault: KeyVaultConfig::from_source(&source)?,
+            notifications: NotificationConfig::from_source(&source)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected text or json".to_string()),
+        }
+    }
+}
+
+/// What to do when the database schema is missing something the importer needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SchemaCheck {
+    Strict,
+    Warn,
+    Off,
+}
+
+impl FromStr for SchemaCheck {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(SchemaCheck::Strict),
+            "warn" => Ok(SchemaCheck::Warn),
+            "off" => Ok(SchemaCheck::Off),
+            _ => Err("expected strict, warn, or off".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DatabaseConfig {
+    url: String,
+    max_connections: u32,
+    min_connections: u32,
+    acquire_timeout: Duration,
+    idle_timeout: Option<Duration>,
+    statement_timeout: Option<Duration>,
+    connect_retries: u32,
+    connect_retry_delay: Duration,
+}
+
+impl DatabaseConfig {
+    fn from_source(source: &ConfigSource) -> Result<Self> {
+        let config = Self {
+            url: source.get("DATABASE_URL", "database.url")?
+                .unwrap_or_else(|| "postgresql://localhost/techstock".to_string()),
+            max_connections: source.get("DB_MAX_CONNECTIONS", "database.max_connections")?.unwrap_or(10),
+            min_connections: source.get("DB_MIN_CONNECTIONS", "database.min_connections")?.unwrap_or(0),
+            acquire_timeout: Duration::from_secs(
+                source.get("DB_ACQUIRE_TIMEOUT_SECS", "database.acquire_timeout_secs")?.unwrap_or(30),
+            ),
+            // 0 disables the timeout
+            idle_timeout: source.get("DB_IDLE_TIMEOUT_SECS", "database.idle_timeout_secs")?
+                .or(Some(600))
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            statement_timeout: source.get("DB_STATEMENT_TIMEOUT_MS", "database.statement_timeout_ms")?
+                .filter(|&ms| ms > 0)
+                .map(Duration::from_millis),
+            connect_retries: source.get("DB_CONNECT_RETRIES", "database.connect_retries")?.unwrap_or(5),
+            connect_retry_delay: Duration::from_secs(
+                source.get("DB_CONNECT_RETRY_DELAY_SECS", "database.connect_retry_delay_secs")?.unwrap_or(1),
+            ),
+        };
+        
+        // A pool that can never hand out a connection only fails later with "pool timed out"
+        if config.max_connections == 0 {
+            return Err(anyhow::anyhow!("Invalid value for DB_MAX_CONNECTIONS (database.max_connections): must be at least 1"));
+        }
+        if config.min_connections > config.max_connections {
+            return Err(anyhow::anyhow!(
+                "Invalid value for DB_MIN_CONNECTIONS (database.min_connections): {} exceeds DB_MAX_CONNECTIONS ({})",
+                config.min_connections, config.max_connections
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Secrets resolved from Azure Key Vault at startup using managed identity.
+#[derive(Debug, Clone)]
+struct KeyVaultConfig {
+    vault_uri: String,
+    database_url_secret: String,
+    // Client ID of a user-assigned identity; the system-assigned identity is used when unset
+    client_id: Option<String>,
+}
+
+impl KeyVaultConfig {
+    fn from_source(source: &ConfigSource) -> Result<Option<Self>> {
+        let Some(vault_uri) = source.get::<String>("KEY_VAULT_URI", "key_vault.uri")? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            vault_uri: vault_uri.trim_end_matches('/').to_string(),
+            database_url_secret: source.get("KEY_VAULT_DATABASE_URL_SECRET", "key_vault.database_url_secret")?
+                .unwrap_or_else(|| "database-url".to_string()),
+            client_id: source.get("AZURE_CLIENT_ID", "key_vault.client_id")?,
+        }))
+    }
+}
+
+/// Outbound webhook (Slack or Teams incoming webhook) for import events.
+#[derive(Debug, Clone)]
+struct NotificationConfig {
+    webhook_url: String,
+    channel: NotificationChannel,
+    events: Vec<NotificationEvent>,
+}
+
+impl NotificationConfig {
+    fn from_source(source: &ConfigSource) -> Result<Option<Self>> {
+        let Some(webhook_url) = source.get::<String>("NOTIFY_WEBHOOK_URL", "notifications.webhook_url")? else {
+            return Ok(None);
+        };
+        let channel = match source.get::<String>("NOTIFY_CHANNEL", "notifications.channel")?
+            .unwrap_or_else(|| "slack".to_string())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "slack" => NotificationChannel::Slack,
+            "teams" => NotificationChannel::Teams,
+            other => return Err(anyhow::anyhow!("Invalid value for NOTIFY_CHANNEL: '{}' (expected slack or teams)", other)),
+        };
+        // Comma-separated list of events to send; all events by default
+        let events = match source.get::<String>("NOTIFY_EVENTS", "notifications.events")? {
+            Some(list) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| NotificationEvent::from_name(name)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid notification event in NOTIFY_EVENTS: '{}'", name)))
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![NotificationEvent::ImportCompleted, NotificationEvent::ImportFailed],
+        };
+        Ok(Some(Self { webhook_url, channel, events }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NotificationChannel {
+    Slack,
+    Teams,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotificationEvent {
+    ImportCompleted,
+    ImportFailed,
+}
+
+impl NotificationEvent {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "import_completed" => Some(NotificationEvent::ImportCompleted),
+            "import_failed" => Some(NotificationEvent::ImportFailed),
+            _ => None,
+        }
+    }
+    
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationEvent::ImportCompleted => "import_completed",
+            NotificationEvent::ImportFailed => "import_failed",
+        }
+    }
+}
+
+struct ConfigSource {
+    path: Option<String>,
+    file: toml::Table,
+}
+
+impl ConfigSource {
+    fn load() -> Result<Self> {
+        // An explicitly configured file must exist; the default one is optional
+        let (path, required) = match env::var("TECHSTOCK_CONFIG") {
+            Ok(path) => (path, true),
+            Err(_) => (DEFAULT_CONFIG_PATH.to_string(), false),
+        };
+        
+        if !Path::new(&path).exists() {
+            if required {
+                return Err(anyhow::anyhow!("Config file not found: {}", path));
+            }
+            return Ok(Self { path: None, file: toml::Table::new() });
+        }
+        
+        let content = std::fs::read_to_string(&path)?;
+        let file = content.parse::<toml::Table>()
+            .map_err(|e| anyhow::anyhow!("Failed to parse config file {}: {}", path, e))?;
+        Ok(Self { path: Some(path), file })
+    }
+    
+    /// Looks up a setting, preferring the environment variable over the dotted file key.
+    fn get<T>(&self, env_key: &str, file_key: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let Some((source, raw)) = self.raw(env_key, file_key)? else {
+            return Ok(None);
+        };
+        
+        raw.parse()
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Invalid value for {}: '{}' ({})", source, raw, e))
+    }
+    
+    fn get_bool(&self, env_key: &str, file_key: &str) -> Result<Option<bool>> {
+        let Some((source, raw)) = self.raw(env_key, file_key)? else {
+            return Ok(None);
+        };
+        
+        match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Some(true)),
+            "false" | "0" | "no" => Ok(Some(false)),
+            _ => Err(anyhow::anyhow!("Invalid boolean for {}: '{}'", source, raw)),
+        }
+    }
+    
+    /// The raw setting and the key it came from, so errors name the source the user actually set.
+    fn raw<'a>(&self, env_key: &'a str, file_key: &'a str) -> Result<Option<(&'a str, String)>> {
+        if let Ok(value) = env::var(env_key) {
+            return Ok(Some((env_key, value)));
+        }
+        Ok(self.lookup(file_key)?.map(|value| (file_key, value)))
+    }
+    
+    fn lookup(&self, dotted_key: &str) -> Result<Option<String>> {
+        let mut parts = dotted_key.split('.');
+        let Some(mut value) = parts.next().and_then(|part| self.file.get(part)) else {
+            return Ok(None);
+        };
+        for part in parts {
+            match value.as_table().and_then(|table| table.get(part)) {
+                Some(next) => value = next,
+                None => return Ok(None),
+            }
+        }
+        match value {
+            toml::Value::String(s) => Ok(Some(s.clone())),
+            // Lists such as notifications.events may be written as arrays of strings
+            toml::Value::Array(items) => items.iter()
+                .map(|item| item.as_str())
+                .collect::<Option<Vec<_>>>()
+                .map(|items| Some(items.join(",")))
+                .ok_or_else(|| anyhow::anyhow!("Invalid value for {}: expected an array of strings", dotted_key)),
+            toml::Value::Table(_) => Err(anyhow::anyhow!("Invalid value for {}: expected a value, found a table", dotted_key)),
+            other => Ok(Some(other.to_string())),
+        }
+    }
+}
+
+// Tables and columns (with Postgres data types) the importer reads or writes
+const REQUIRED_COLUMNS: &[(&str, &[(&str, &str)])] = &[
+    ("subscription", &[
+        ("id", "bigint"),
+        ("name", "text"),
+        ("azure_subscription_id", "text"),
+    ]),
+    ("resource_group", &[("id", "bigint"), ("name", "text"), ("subscription_id", "bigint")]),
+    ("application", &[("id", "bigint"), ("code", "text"), ("name", "text"), ("owner_email", "text")]),
+    ("resource", &[
+        ("id", "bigint"),
+        ("name", "text"),
+        ("type", "text"),
+        ("kind", "text"),
+        ("location", "text"),
+        ("subscription_id", "bigint"),
+        ("resource_group_id", "bigint"),
+        ("tags_json", "jsonb"),
+        ("extended_location", "text"),
+        ("vendor", "text"),
+        ("environment", "text"),
+        ("provisioner", "text"),
+        ("azure_id", "text"),
+    ]),
+    ("resource_tag", &[("resource_id", "bigint"), ("key", "text"), ("value", "text")]),
+    ("resource_relationship", &[
+        ("parent_id", "bigint"),
+        ("child_id", "bigint"),
+        ("relation_type", "text"),
+    ]),
+    ("resource_application_map", &[
+        ("resource_id", "bigint"),
+        ("application_id", "bigint"),
+        ("relation_type", "text"),
+    ]),
+];
+
+// Indexes the importer relies on (ON CONFLICT targets) or that queries expect
+const REQUIRED_INDEXES: &[&str] = &[
+    "resource_tag_pkey",
+    "resource_application_map_pkey",
+    "application_code_upper_key",
+    "resource_azure_id_key",
+    "resource_relationship_pkey",
+    "idx_resource_type",
+    "idx_resource_location",
+    "idx_resource_tags_gin",
+    "idx_resource_tag_key_val",
+];
 
 #[derive(Debug, Deserialize)]
 struct CsvRecord {
@@ -25,6 +409,258 @@ struct CsvRecord {
     tags: String,
     #[serde(rename = "extendedLocation")]
     extended_location: Option<String>,
+    // Optional column, present when the Resource Graph query projects subscriptionId
+    #[serde(rename = "subscriptionId", default)]
+    subscription_id: Option<String>,
+    // Optional column with the full ARM resource ID
+    #[serde(rename = "id", default)]
+    azure_id: Option<String>,
+}
+
+/// A parsed Azure Resource Manager ID, e.g.
+/// `/subscriptions/{guid}/resourceGroups/{rg}/providers/Microsoft.Compute/virtualMachines/{name}`.
+/// Child resources repeat the type/name pair (`.../virtualMachines/vm1/extensions/ext1`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AzureResourceId {
+    subscription_id: String,
+    resource_group: String,
+    provider_namespace: String,
+    // (type, name) pairs from the top-level resource down to this one
+    segments: Vec<(String, String)>,
+}
+
+impl AzureResourceId {
+    /// Full resource type, e.g. `Microsoft.Compute/virtualMachines/extensions`.
+    fn resource_type(&self) -> String {
+        let types: Vec<&str> = self.segments.iter().map(|(t, _)| t.as_str()).collect();
+        format!("{}/{}", self.provider_namespace, types.join("/"))
+    }
+    
+    fn name(&self) -> &str {
+        // Parsing guarantees at least one segment
+        &self.segments[self.segments.len() - 1].1
+    }
+    
+    /// The enclosing resource for child resources, `None` for top-level resources.
+    fn parent(&self) -> Option<AzureResourceId> {
+        if self.segments.len() < 2 {
+            return None;
+        }
+        let mut parent = self.clone();
+        parent.segments.pop();
+        Some(parent)
+    }
+}
+
+impl FromStr for AzureResourceId {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().trim_matches('/').split('/').collect();
+        let invalid = |reason: &str| format!("invalid Azure resource ID '{}': {}", s, reason);
+        
+        if parts.len() < 8 {
+            return Err(invalid("expected /subscriptions/{id}/resourceGroups/{name}/providers/{namespace}/{type}/{name}"));
+        }
+        if !parts[0].eq_ignore_ascii_case("subscriptions") || !is_guid(parts[1]) {
+            return Err(invalid("missing subscription GUID"));
+        }
+        if !parts[2].eq_ignore_ascii_case("resourceGroups") || parts[3].is_empty() {
+            return Err(invalid("missing resource group"));
+        }
+        if !parts[4].eq_ignore_ascii_case("providers") || parts[5].is_empty() {
+            return Err(invalid("missing provider namespace"));
+        }
+        
+        let rest = &parts[6..];
+        if !rest.len().is_multiple_of(2) || rest.iter().any(|p| p.is_empty()) {
+            return Err(invalid("type and name segments must come in pairs"));
+        }
+        if rest.iter().step_by(2).any(|t| t.eq_ignore_ascii_case("providers")) {
+            return Err(invalid("malformed extension resource ID"));
+        }
+        
+        Ok(Self {
+            subscription_id: parts[1].to_ascii_lowercase(),
+            resource_group: parts[3].to_string(),
+            provider_namespace: parts[5].to_string(),
+            segments: rest.chunks(2).map(|pair| (pair[0].to_string(), pair[1].to_string())).collect(),
+        })
+    }
+}
+
+impl std::fmt::Display for AzureResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/subscriptions/{}/resourceGroups/{}/providers/{}",
+            self.subscription_id, self.resource_group, self.provider_namespace)?;
+        for (resource_type, name) in &self.segments {
+            write!(f, "/{}/{}", resource_type, name)?;
+        }
+        Ok(())
+    }
+}
+
+impl CsvRecord {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("resource name is empty".to_string());
+        }
+        if self.resource_type.trim().is_empty() {
+            return Err("resource type is empty".to_string());
+        }
+        if self.subscription.trim().is_empty() {
+            return Err("subscription is empty".to_string());
+        }
+        if self.resource_group.trim().is_empty() {
+            return Err("resource group is empty".to_string());
+        }
+        if !is_valid_location(&self.location) {
+            return Err(format!("invalid location '{}'", self.location));
+        }
+        
+        if let Some(id) = self.azure_id.as_deref()
+            && is_extension_resource_id(id)
+        {
+            log::debug!("Resource {} has an extension resource ID, skipping parent linking: {}",
+                self.name, id.trim());
+        }
+        
+        // The ARM ID must agree with the other columns it duplicates
+        if let Some(azure_id) = self.azure_resource_id()? {
+            if !azure_id.resource_group.eq_ignore_ascii_case(self.resource_group.trim()) {
+                return Err(format!("resource ID resource group '{}' does not match '{}'",
+                    azure_id.resource_group, self.resource_group));
+            }
+            if !azure_id.name().eq_ignore_ascii_case(self.name.trim()) {
+                return Err(format!("resource ID name '{}' does not match '{}'", azure_id.name(), self.name));
+            }
+            if let Some(guid) = self.subscription_id.as_deref().map(str::trim).filter(|g| !g.is_empty())
+                && !azure_id.subscription_id.eq_ignore_ascii_case(guid)
+            {
+                return Err(format!("resource ID subscription '{}' does not match '{}'",
+                    azure_id.subscription_id, guid));
+            }
+        }
+        Ok(())
+    }
+    
+    fn azure_resource_id(&self) -> Result<Option<AzureResourceId>, String> {
+        match self.azure_id.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            // Extension resources hang off another resource's ID and don't parse as one
+            Some(id) if is_extension_resource_id(id) => Ok(None),
+            Some(id) => id.parse().map(Some),
+        }
+    }
+    
+    /// The ARM ID as stored in `resource.azure_id`: lowercased, since ARM IDs are case-insensitive.
+    /// Extension IDs are stored too, so re-imports update them in place.
+    fn stored_azure_id(&self) -> Option<String> {
+        match self.azure_id.as_deref().map(str::trim) {
+            Some(id) if is_extension_resource_id(id) => Some(format!("/{}", id.trim_matches('/').to_lowercase())),
+            _ => self.azure_resource_id().ok().flatten().map(|id| id.to_string().to_lowercase()),
+        }
+    }
+    
+    fn azure_subscription_id(&self) -> Option<String> {
+        let value = match self.subscription_id.as_deref().map(str::trim) {
+            Some(value) if !value.is_empty() => value,
+            // Fall back to the GUID embedded in the ARM ID
+            _ => return self.azure_resource_id().ok().flatten().map(|id| id.subscription_id),
+        };
+        if !is_guid(value) {
+            log::warn!("Ignoring invalid subscription ID '{}' for resource {}", value, self.name);
+            return None;
+        }
+        Some(value.to_ascii_lowercase())
+    }
+}
+
+/// Extension resources attach a second provider to another resource, e.g.
+/// `.../virtualMachines/vm1/providers/Microsoft.Insights/diagnosticSettings/ds1`.
+fn is_extension_resource_id(id: &str) -> bool {
+    let parts: Vec<&str> = id.trim().trim_matches('/').split('/').collect();
+    // A nested "providers" keyword can only appear where a resource type would
+    let Some(split) = (8..parts.len())
+        .step_by(2)
+        .find(|&i| parts[i].eq_ignore_ascii_case("providers"))
+    else {
+        return false;
+    };
+    let (base, extension) = parts.split_at(split);
+    // The extension must be providers/{namespace}/{type}/{name}[/{type}/{name}...]
+    base.join("/").parse::<AzureResourceId>().is_ok()
+        && extension.len() >= 4
+        && extension.len().is_multiple_of(2)
+        && extension.iter().all(|p| !p.is_empty())
+}
+
+fn is_guid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+// Azure region names, either programmatic ("southeastasia") or display form ("Southeast Asia")
+fn is_valid_location(location: &str) -> bool {
+    let location = location.trim();
+    !location.is_empty()
+        && location.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+}
+
+// Azure tag names are limited to 512 characters and may not contain < > % & \ ? /
+fn is_valid_tag_key(key: &str) -> bool {
+    !key.trim().is_empty()
+        && key.chars().count() <= 512
+        && !key.contains(['<', '>', '%', '&', '\\', '?', '/'])
+}
+
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.contains(char::is_whitespace)
+}
+
+/// Deployment environment normalized from the free-text `Environment` tag, so that
+/// `prd`, `PROD`, and `Production` are counted as one environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Environment {
+    Dev,
+    Test,
+    Uat,
+    Prod,
+    Unknown,
+}
+
+impl Environment {
+    fn from_tag(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dev" | "develop" | "development" => Environment::Dev,
+            "test" | "tst" | "testing" | "qa" | "sit" => Environment::Test,
+            "uat" | "staging" | "stage" | "stg" | "preprod" | "pre-prod" => Environment::Uat,
+            "prd" | "prod" | "production" | "live" => Environment::Prod,
+            _ => Environment::Unknown,
+        }
+    }
+    
+    // Stored form, matching the existing 'PRD'/'UAT' convention
+    fn as_str(self) -> &'static str {
+        match self {
+            Environment::Dev => "DEV",
+            Environment::Test => "TEST",
+            Environment::Uat => "UAT",
+            Environment::Prod => "PRD",
+            Environment::Unknown => "UNKNOWN",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -33,66 +669,409 @@ struct ParsedTags {
     tags_json: Value,
 }
 
+#[derive(Debug)]
+struct ImportStats {
+    total_records: usize,
+    processed: usize,
+    skipped: usize,
+    started_at: Instant,
+    parse_time: Duration,
+    db_time: Duration,
+}
+
+impl ImportStats {
+    fn new(total_records: usize) -> Self {
+        Self {
+            total_records,
+            processed: 0,
+            skipped: 0,
+            started_at: Instant::now(),
+            parse_time: Duration::ZERO,
+            db_time: Duration::ZERO,
+        }
+    }
+
+    fn rows_per_sec(&self) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.processed as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        let rate = self.rows_per_sec();
+        if rate > 0.0 {
+            let remaining = self.total_records.saturating_sub(self.processed);
+            Some(Duration::from_secs_f64(remaining as f64 / rate))
+        } else {
+            None
+        }
+    }
+
+    fn summary(&self) -> String {
+        let eta = self.eta()
+            .map(|d| format!("{}m{:02}s", d.as_secs() / 60, d.as_secs() % 60))
+            .unwrap_or_else(|| "unknown".to_string());
+        format!(
+            "{}/{} records, {:.1} rows/sec, parse {:.2}s, db {:.2}s, elapsed {:.1}s, ETA {}",
+            self.processed,
+            self.total_records,
+            self.rows_per_sec(),
+            self.parse_time.as_secs_f64(),
+            self.db_time.as_secs_f64(),
+            self.started_at.elapsed().as_secs_f64(),
+            eta,
+        )
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Load environment variables first so .env can override the config file
+    dotenv::dotenv().ok();
+    let config = Config::load()?;
+    
     // Initialize logging
-    pretty_env_logger::init();
+    init_logging(&config);
     
-    // Load environment variables
-    dotenv::dotenv().ok();
+    match &config.file_path {
+        Some(path) => log::info!("Loaded configuration from {}", path),
+        None => log::debug!("No config file found, using environment and defaults"),
+    }
     
-    let database_url = env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "postgresql://localhost/techstock".to_string());
+    SLOW_QUERY_THRESHOLD_MS.store(config.slow_query_threshold_ms, Ordering::Relaxed);
+    
+    // Any failure from here on is reported through notifications
+    let result = run_import(&config).await;
+    let csv_path = &config.csv_path;
+    
+    if let Some(notifications) = &config.notifications {
+        let (event, title, text) = match &result {
+            Ok(stats) => (
+                NotificationEvent::ImportCompleted,
+                "TechStock import completed",
+                format!("Imported {} records from {} ({} skipped as invalid) in {:.1}s",
+                    stats.processed - stats.skipped, csv_path, stats.skipped,
+                    stats.started_at.elapsed().as_secs_f64()),
+            ),
+            Err(e) => (
+                NotificationEvent::ImportFailed,
+                "TechStock import failed",
+                format!("Import from {} failed: {}", csv_path, e),
+            ),
+        };
+        // A broken webhook should not turn a successful import into a failure
+        if let Err(e) = send_notification(notifications, event, title, &text).await {
+            log::warn!("Failed to send {} notification: {}", event.as_str(), e);
+        }
+    }
     
-    log::info!("Connecting to database: {}", database_url);
-    log::debug!("Environment variables loaded from .env file");
+    result?;
+    log::info!("Import completed successfully!");
+    
+    Ok(())
+}
+
+async fn run_import(config: &Config) -> Result<ImportStats> {
+    // Resolve secrets from Key Vault when configured
+    let mut database = config.database.clone();
+    if let Some(key_vault) = &config.key_vault {
+        log::info!("Resolving database URL from Key Vault secret '{}' in {}",
+            key_vault.database_url_secret, key_vault.vault_uri);
+        database.url = fetch_key_vault_secret(key_vault, &key_vault.database_url_secret).await?;
+    }
+    
+    log::info!("Connecting to database: {}", redact_url(&database.url));
     
     // Connect to database
     log::debug!("Attempting database connection...");
-    let pool = PgPool::connect(&database_url).await?;
+    let pool = connect_database(&database).await?;
     log::info!("Database connection established successfully");
     
-    // Run migrations/create tables if needed
-    log::info!("Setting up database tables...");
-    setup_database(&pool).await?;
-    log::debug!("Database setup completed");
+    // Apply schema migrations when enabled
+    if config.run_migrations {
+        log::info!("Running database migrations...");
+        run_migrations(&pool).await?;
+        log::info!("Database migrations applied");
+    } else {
+        log::debug!("RUN_MIGRATIONS not enabled, skipping migrations");
+    }
+    
+    // Verify the schema before importing anything
+    let schema_check = config.schema_check;
+    if schema_check != SchemaCheck::Off {
+        let problems = verify_schema(&pool).await?;
+        if !problems.is_empty() {
+            for problem in &problems {
+                log::error!("Schema check: {}", problem);
+            }
+            if schema_check == SchemaCheck::Warn {
+                log::warn!("Schema check found {} problem(s), continuing because SCHEMA_CHECK=warn", problems.len());
+            } else {
+                return Err(anyhow::anyhow!(
+                    "Database schema is out of date ({} problem(s)); run with RUN_MIGRATIONS=true or set SCHEMA_CHECK=warn",
+                    problems.len()
+                ));
+            }
+        } else {
+            log::info!("Database schema check passed");
+        }
+    }
     
     // Import CSV data
-    let csv_path = "datasets/AzureResourceGraphFormattedResults-Query.csv";
+    let csv_path = &config.csv_path;
     log::info!("Starting CSV import from: {}", csv_path);
     
-    import_csv_data(&pool, csv_path).await?;
+    import_csv_data(&pool, csv_path).await
+}
+
+fn init_logging(config: &Config) {
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Some(filters) = &config.log_level {
+        builder.parse_filters(filters);
+    }
     
-    log::info!("Import completed successfully!");
+    if config.log_format == LogFormat::Json {
+        // One JSON object per line for log shippers (ELK, Loki, ...)
+        builder.format(|buf, record| {
+            use std::io::Write;
+            let entry = serde_json::json!({
+                "timestamp": buf.timestamp_millis().to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", entry)
+        });
+    }
     
-    Ok(())
+    builder.init();
+}
+
+async fn fetch_key_vault_secret(config: &KeyVaultConfig, secret_name: &str) -> Result<String> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        access_token: String,
+    }
+    
+    #[derive(Deserialize)]
+    struct SecretResponse {
+        value: String,
+    }
+    
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    
+    // App Service and Container Apps expose IDENTITY_ENDPOINT; VMs and AKS use IMDS
+    let mut token_request = match (env::var("IDENTITY_ENDPOINT"), env::var("IDENTITY_HEADER")) {
+        (Ok(endpoint), Ok(header)) => client
+            .get(endpoint)
+            .header("X-IDENTITY-HEADER", header)
+            .query(&[("api-version", "2019-08-01"), ("resource", KEY_VAULT_RESOURCE)]),
+        _ => client
+            .get(IMDS_TOKEN_URL)
+            .header("Metadata", "true")
+            .query(&[("api-version", "2018-02-01"), ("resource", KEY_VAULT_RESOURCE)]),
+    };
+    if let Some(client_id) = &config.client_id {
+        token_request = token_request.query(&[("client_id", client_id)]);
+    }
+    
+    log::debug!("Requesting managed identity token for Key Vault");
+    let token: TokenResponse = token_request
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire managed identity token: {}", e))?
+        .json()
+        .await?;
+    
+    let secret_url = format!("{}/secrets/{}", config.vault_uri, secret_name);
+    log::debug!("Fetching Key Vault secret: {}", secret_url);
+    let secret: SecretResponse = client
+        .get(&secret_url)
+        .bearer_auth(token.access_token)
+        .query(&[("api-version", KEY_VAULT_API_VERSION)])
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| anyhow::anyhow!("Failed to read Key Vault secret '{}': {}", secret_name, e))?
+        .json()
+        .await?;
+    
+    Ok(secret.value)
 }
 
-async fn setup_database(pool: &PgPool) -> Result<()> {
-    // Read and execute the SQL schema
-    log::debug!("Reading SQL schema from sql/create_tables.sql");
-    let sql_content = tokio::fs::read_to_string("sql/create_tables.sql").await?;
-    log::debug!("SQL schema file loaded, {} bytes", sql_content.len());
+async fn send_notification(
+    config: &NotificationConfig,
+    event: NotificationEvent,
+    title: &str,
+    text: &str,
+) -> Result<()> {
+    if !config.events.contains(&event) {
+        log::debug!("Notification event {} not enabled, skipping", event.as_str());
+        return Ok(());
+    }
     
-    // Split by semicolon and execute each statement
-    let statements: Vec<&str> = sql_content.split(';').collect();
-    log::debug!("Executing {} SQL statements", statements.len());
+    let payload = match config.channel {
+        NotificationChannel::Slack => serde_json::json!({
+            "text": format!("*{}*\n{}", title, text),
+        }),
+        NotificationChannel::Teams => serde_json::json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": title,
+            "themeColor": if event == NotificationEvent::ImportFailed { "D13438" } else { "2EB886" },
+            "title": title,
+            "text": text,
+        }),
+    };
+    
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    client
+        .post(&config.webhook_url)
+        .json(&payload)
+        .send()
+        .await?
+        .error_for_status()?;
     
-    for (i, statement) in statements.iter().enumerate() {
-        let statement = statement.trim();
-        if !statement.is_empty() && !statement.starts_with("--") {
-            log::debug!("Executing SQL statement {}: {}", i + 1, statement.chars().take(50).collect::<String>());
-            match sqlx::query(statement).execute(pool).await {
-                Ok(_) => log::debug!("SQL statement {} executed successfully", i + 1),
-                Err(e) => log::debug!("SQL statement {} failed (ignoring): {}", i + 1, e),
+    log::info!("Sent {} notification to {:?} webhook", event.as_str(), config.channel);
+    Ok(())
+}
+
+// Hide the password when logging a connection string
+fn redact_url(url: &str) -> String {
+    match (url.find("://"), url.rfind('@')) {
+        (Some(scheme_end), Some(at)) if at > scheme_end => {
+            let credentials = &url[scheme_end + 3..at];
+            match credentials.split_once(':') {
+                Some((user, _)) => format!("{}{}:***{}", &url[..scheme_end + 3], user, &url[at..]),
+                None => url.to_string(),
             }
         }
+        _ => url.to_string(),
     }
+}
+
+async fn connect_database(config: &DatabaseConfig) -> Result<PgPool> {
+    log::debug!("Pool settings - max: {}, min: {}, acquire timeout: {:?}, idle timeout: {:?}, statement timeout: {:?}",
+        config.max_connections, config.min_connections, config.acquire_timeout,
+        config.idle_timeout, config.statement_timeout);
     
+    let mut connect_options = PgConnectOptions::from_str(&config.url)?;
+    if let Some(timeout) = config.statement_timeout {
+        connect_options = connect_options
+            .options([("statement_timeout", format!("{}ms", timeout.as_millis()))]);
+    }
+    
+    // Postgres may still be starting (docker-compose, Kubernetes), so back off and retry
+    let mut delay = config.connect_retry_delay;
+    let mut attempt = 0;
+    loop {
+        let result = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .connect_with(connect_options.clone())
+            .await;
+        
+        match result {
+            Ok(pool) => return Ok(pool),
+            // Wrong credentials or a missing database won't fix themselves, so only retry transient errors
+            Err(e) if attempt < config.connect_retries && is_transient_error(&e) => {
+                attempt += 1;
+                log::warn!("Database connection failed (attempt {}/{}), retrying in {:?}: {}",
+                    attempt, config.connect_retries, delay, e);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_CONNECT_RETRY_DELAY);
+            }
+            Err(e) if is_transient_error(&e) => {
+                log::error!("Giving up connecting to database after {} retries", attempt);
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+async fn run_migrations(pool: &PgPool) -> Result<()> {
+    log::debug!("Applying {} embedded migrations", MIGRATOR.iter().count());
+    MIGRATOR.run(pool).await?;
     Ok(())
 }
 
-async fn import_csv_data(pool: &PgPool, csv_path: &str) -> Result<()> {
+async fn verify_schema(pool: &PgPool) -> Result<Vec<String>> {
+    let rows = sqlx::query(
+        "SELECT table_name::text, column_name::text, data_type::text
+         FROM information_schema.columns
+         WHERE table_schema = current_schema()"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let mut columns: HashMap<(String, String), String> = HashMap::new();
+    for row in rows {
+        columns.insert((row.get(0), row.get(1)), row.get(2));
+    }
+    
+    let mut problems = Vec::new();
+    for (table, expected_columns) in REQUIRED_COLUMNS {
+        if !columns.keys().any(|(t, _)| t == table) {
+            problems.push(format!("missing table '{}'", table));
+            continue;
+        }
+        for (column, expected_type) in *expected_columns {
+            match columns.get(&(table.to_string(), column.to_string())) {
+                None => problems.push(format!("missing column '{}.{}'", table, column)),
+                Some(actual) if actual != expected_type => problems.push(format!(
+                    "column '{}.{}' has type '{}', expected '{}'", table, column, actual, expected_type
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+    
+    let index_rows = sqlx::query("SELECT indexname::text FROM pg_indexes WHERE schemaname = current_schema()")
+        .fetch_all(pool)
+        .await?;
+    let indexes: Vec<String> = index_rows.iter().map(|row| row.get(0)).collect();
+    for index in REQUIRED_INDEXES {
+        if !indexes.iter().any(|i| i == index) {
+            problems.push(format!("missing index '{}'", index));
+        }
+    }
+    
+    // Data-only migrations leave no columns or indexes behind, so compare versions too
+    let expected_version = MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0);
+    let tracked: Option<String> = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations')::text")
+        .fetch_one(pool)
+        .await?;
+    let applied_version: Option<i64> = match tracked {
+        Some(_) => sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+            .fetch_one(pool)
+            .await?,
+        None => None,
+    };
+    match applied_version {
+        None => problems.push(format!("no migrations recorded, expected version {}", expected_version)),
+        Some(version) if version < expected_version => problems.push(format!(
+            "database is at migration version {}, expected {}", version, expected_version
+        )),
+        Some(_) => {}
+    }
+    
+    log::debug!("Schema check inspected {} columns and {} indexes, migration version {:?}",
+        columns.len(), indexes.len(), applied_version);
+    Ok(problems)
+}
+
+async fn import_csv_data(pool: &PgPool, csv_path: &str) -> Result<ImportStats> {
     log::debug!("Checking if CSV file exists: {}", csv_path);
     if !Path::new(csv_path).exists() {
         log::error!("CSV file not found: {}", csv_path);
@@ -100,6 +1079,14 @@ async fn import_csv_data(pool: &PgPool, csv_path: &str) -> Result<()> {
     }
     log::debug!("CSV file found, initializing reader");
     
+    // Count rows up front so progress can report an ETA
+    let total_records = ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(csv_path)?
+        .records()
+        .count();
+    log::info!("Found {} records to import", total_records);
+    
     let mut reader = ReaderBuilder::new()
         .has_headers(true)
         .from_path(csv_path)?;
@@ -111,92 +1098,139 @@ async fn import_csv_data(pool: &PgPool, csv_path: &str) -> Result<()> {
     log::debug!("Initialized caches for subscriptions, resource groups, and applications");
     
     let mut record_count = 0;
+    let mut skipped_count = 0;
+    let mut stats = ImportStats::new(total_records);
+    let mut records = reader.deserialize();
+    let mut pending_parents: Vec<(i64, String)> = Vec::new();
     
-    for result in reader.deserialize() {
+    loop {
+        let parse_started = Instant::now();
+        let Some(result) = records.next() else {
+            break;
+        };
         let record: CsvRecord = result?;
         record_count += 1;
+        let validation = record.validate();
+        stats.parse_time += parse_started.elapsed();
         
-        if record_count % 100 == 0 {
-            log::info!("Processed {} records", record_count);
-            log::debug!("Cache stats - Subscriptions: {}, Resource Groups: {}, Applications: {}", 
-                subscription_cache.len(), resource_group_cache.len(), application_cache.len());
-        }
-        
-        if record_count % 10 == 0 {
-            log::debug!("Processing record {}: {} ({})", record_count, record.name, record.resource_type);
-        }
-        
-        // Parse tags
-        log::debug!("Parsing tags for resource: {}", record.name);
-        let parsed_tags = parse_tags(&record.tags)?;
-        log::debug!("Parsed {} tags for resource: {}", parsed_tags.tags.len(), record.name);
-        
-        // Get or create subscription
-        log::debug!("Getting/creating subscription: {}", record.subscription);
-        let subscription_id = get_or_create_subscription(
-            pool, 
-            &record.subscription, 
-            &mut subscription_cache
-        ).await?;
-        log::debug!("Subscription ID: {}", subscription_id);
-        
-        // Get or create resource group
-        log::debug!("Getting/creating resource group: {}", record.resource_group);
-        let resource_group_id = get_or_create_resource_group(
-            pool,
-            &record.resource_group,
-            subscription_id,
-            &mut resource_group_cache,
-        ).await?;
-        log::debug!("Resource group ID: {}", resource_group_id);
-        
-        // Get or create application if AppID exists
-        let application_id = if let Some(app_id) = parsed_tags.tags.get("AppID") {
-            log::debug!("Getting/creating application: {}", app_id);
-            let app_id_result = get_or_create_application(
+        if let Err(reason) = validation {
+            log::warn!("Skipping record {} ({}): {}", record_count, record.name, reason);
+            skipped_count += 1;
+            stats.skipped = skipped_count;
+        } else {
+            if record_count % 10 == 0 {
+                log::debug!("Processing record {}: {} ({})", record_count, record.name, record.resource_type);
+            }
+            
+            // Parse tags
+            let tags_started = Instant::now();
+            log::debug!("Parsing tags for resource: {}", record.name);
+            let parsed_tags = parse_tags(&record.tags)?;
+            log::debug!("Parsed {} tags for resource: {}", parsed_tags.tags.len(), record.name);
+            stats.parse_time += tags_started.elapsed();
+            
+            let db_started = Instant::now();
+            
+            // Get or create subscription
+            log::debug!("Getting/creating subscription: {}", record.subscription);
+            let azure_subscription_id = record.azure_subscription_id();
+            let subscription_id = get_or_create_subscription(
+                pool, 
+                &record.subscription, 
+                azure_subscription_id.as_deref(),
+                &mut subscription_cache
+            ).await?;
+            log::debug!("Subscription ID: {}", subscription_id);
+            
+            // Get or create resource group
+            log::debug!("Getting/creating resource group: {}", record.resource_group);
+            let resource_group_id = get_or_create_resource_group(
                 pool,
-                app_id,
+                &record.resource_group,
+                subscription_id,
+                &mut resource_group_cache,
+            ).await?;
+            log::debug!("Resource group ID: {}", resource_group_id);
+            
+            // Get or create application if AppID exists
+            let application_id = if let Some(app_id) = parsed_tags.tags.get("AppID") {
+                log::debug!("Getting/creating application: {}", app_id);
+                let app_id_result = get_or_create_application(
+                    pool,
+                    app_id,
+                    &parsed_tags,
+                    &mut application_cache,
+                ).await?;
+                log::debug!("Application ID: {}", app_id_result);
+                Some(app_id_result)
+            } else {
+                log::debug!("No AppID found in tags for resource: {}", record.name);
+                None
+            };
+            
+            // Insert resource
+            log::debug!("Inserting resource: {}", record.name);
+            let (resource_id, inserted) = insert_resource(
+                pool,
+                &record,
                 &parsed_tags,
-                &mut application_cache,
+                subscription_id,
+                resource_group_id,
             ).await?;
-            log::debug!("Application ID: {}", app_id_result);
-            Some(app_id_result)
-        } else {
-            log::debug!("No AppID found in tags for resource: {}", record.name);
-            None
-        };
-        
-        // Insert resource
-        log::debug!("Inserting resource: {}", record.name);
-        let resource_id = insert_resource(
-            pool,
-            &record,
-            &parsed_tags,
-            subscription_id,
-            resource_group_id,
-        ).await?;
-        log::debug!("Resource inserted with ID: {}", resource_id);
+            log::debug!("Resource inserted with ID: {}", resource_id);
+            
+            // An updated resource gets its tags and application link from this row only
+            if !inserted {
+                clear_resource_tags_and_links(pool, resource_id).await?;
+            }
+            
+            // Remember child resources so they can be linked once every parent is imported
+            if let Some(parent) = record.azure_resource_id().ok().flatten().and_then(|id| id.parent()) {
+                pending_parents.push((resource_id, parent.to_string()));
+            }
+            
+            // Insert resource tags
+            log::debug!("Inserting {} tags for resource ID: {}", parsed_tags.tags.len(), resource_id);
+            insert_resource_tags(pool, resource_id, &parsed_tags).await?;
+            log::debug!("Tags inserted successfully for resource ID: {}", resource_id);
+            
+            // Link resource to application if exists
+            if let Some(app_id) = application_id {
+                log::debug!("Linking resource {} to application {}", resource_id, app_id);
+                link_resource_to_application(pool, resource_id, app_id).await?;
+                log::debug!("Resource-application link created successfully");
+            }
+            
+            stats.db_time += db_started.elapsed();
+        }
         
-        // Insert resource tags
-        log::debug!("Inserting {} tags for resource ID: {}", parsed_tags.tags.len(), resource_id);
-        insert_resource_tags(pool, resource_id, &parsed_tags).await?;
-        log::debug!("Tags inserted successfully for resource ID: {}", resource_id);
+        stats.processed = record_count;
         
-        // Link resource to application if exists
-        if let Some(app_id) = application_id {
-            log::debug!("Linking resource {} to application {}", resource_id, app_id);
-            link_resource_to_application(pool, resource_id, app_id).await?;
-            log::debug!("Resource-application link created successfully");
+        if record_count % 100 == 0 {
+            log::info!("Processed {}", stats.summary());
+            log::debug!("Cache stats - Subscriptions: {}, Resource Groups: {}, Applications: {}", 
+                subscription_cache.len(), resource_group_cache.len(), application_cache.len());
         }
     }
     
-    log::info!("Successfully imported {} records", record_count);
-    Ok(())
+    log::info!("Successfully imported {} records ({} skipped as invalid)",
+        record_count - skipped_count, skipped_count);
+    
+    if !pending_parents.is_empty() {
+        let linked = link_child_resources(pool, &pending_parents).await?;
+        log::info!("Recorded {} new parent/child links for {} child resources",
+            linked, pending_parents.len());
+    }
+    log::info!("Import throughput: {}", stats.summary());
+    log::info!("Transient database errors retried: {}", DB_RETRY_COUNT.load(Ordering::Relaxed));
+    log::info!("Slow queries (>= {}ms): {}",
+        SLOW_QUERY_THRESHOLD_MS.load(Ordering::Relaxed), SLOW_QUERY_COUNT.load(Ordering::Relaxed));
+    Ok(stats)
 }
 
 fn parse_tags(tags_str: &str) -> Result<ParsedTags> {
     log::debug!("Parsing tags string: {}", tags_str.chars().take(100).collect::<String>());
-    let tags_json: Value = if tags_str == "null" || tags_str.is_empty() {
+    let mut tags_json: Value = if tags_str == "null" || tags_str.is_empty() {
         log::debug!("Empty or null tags, using empty object");
         serde_json::json!({})
     } else {
@@ -214,8 +1248,16 @@ fn parse_tags(tags_str: &str) -> Result<ParsedTags> {
     
     let mut tags = HashMap::new();
     
-    if let Value::Object(map) = &tags_json {
-        for (key, value) in map {
+    if let Value::Object(map) = &mut tags_json {
+        // Drop invalid keys from the JSON too, so tags_json and resource_tag agree
+        map.retain(|key, _| {
+            let valid = is_valid_tag_key(key);
+            if !valid {
+                log::warn!("Ignoring invalid tag key: {}", key);
+            }
+            valid
+        });
+        for (key, value) in map.iter() {
             if let Some(str_value) = value.as_str() {
                 tags.insert(key.clone(), str_value.to_string());
             } else if !value.is_null() {
@@ -230,39 +1272,201 @@ fn parse_tags(tags_str: &str) -> Result<ParsedTags> {
     })
 }
 
+fn is_transient_error(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(db_err) => matches!(
+            db_err.code().as_deref(),
+            // serialization_failure, deadlock_detected, admin_shutdown, cannot_connect_now
+            Some("40001") | Some("40P01") | Some("57P01") | Some("57P03")
+        ),
+        _ => false,
+    }
+}
+
+// Transient errors that can surface after the statement was already committed
+fn may_have_been_applied(err: &sqlx::Error) -> bool {
+    match err {
+        sqlx::Error::Io(_) => true,
+        sqlx::Error::Database(db_err) => db_err.code().as_deref() == Some("57P01"),
+        _ => false,
+    }
+}
+
+async fn with_retry<T, F, Fut>(
+    operation: &str,
+    sql: &str,
+    params: &[LogParam<'_>],
+    f: F,
+) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    with_retry_policy(operation, sql, params, true, f).await
+}
+
+/// Runs a statement with retries. Statements that are not idempotent (plain INSERTs
+/// without a conflict target) are only retried when the error proves they did not run,
+/// since a connection lost after commit would otherwise insert the row twice.
+async fn with_retry_policy<T, F, Fut>(
+    operation: &str,
+    sql: &str,
+    params: &[LogParam<'_>],
+    idempotent: bool,
+    mut f: F,
+) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let result = f().await;
+        log_if_slow(operation, sql, params, started.elapsed());
+        
+        match result {
+            Err(e) if attempt < DB_MAX_RETRIES
+                && is_transient_error(&e)
+                && (idempotent || !may_have_been_applied(&e)) => {
+                attempt += 1;
+                DB_RETRY_COUNT.fetch_add(1, Ordering::Relaxed);
+
+                // Exponential backoff with up to 100% jitter
+                let base = DB_RETRY_BASE_DELAY_MS << (attempt - 1);
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.subsec_nanos() as u64)
+                    .unwrap_or(0);
+                let delay = base + nanos % (base + 1);
+
+                log::warn!("Transient database error during {} (attempt {}/{}), retrying in {}ms: {}",
+                    operation, attempt, DB_MAX_RETRIES, delay, e);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A bound parameter as shown in slow-query logs. Ids are logged as-is; imported text
+/// (names, tag values, emails) only by length, so logs don't leak inventory data.
+#[derive(Debug, Clone, Copy)]
+enum LogParam<'a> {
+    Id(i64),
+    Text(Option<&'a str>),
+    Json(&'a Value),
+    Array(usize),
+}
+
+impl std::fmt::Display for LogParam<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogParam::Id(id) => write!(f, "{}", id),
+            LogParam::Text(None) => write!(f, "NULL"),
+            LogParam::Text(Some(text)) => write!(f, "<text, {} chars>", text.chars().count()),
+            LogParam::Json(Value::Object(map)) => write!(f, "<jsonb, {} keys>", map.len()),
+            LogParam::Json(_) => write!(f, "<jsonb>"),
+            LogParam::Array(len) => write!(f, "<array, {} items>", len),
+        }
+    }
+}
+
+fn log_if_slow(operation: &str, sql: &str, params: &[LogParam<'_>], elapsed: Duration) {
+    let threshold_ms = SLOW_QUERY_THRESHOLD_MS.load(Ordering::Relaxed);
+    if elapsed.as_millis() < threshold_ms as u128 {
+        return;
+    }
+    
+    SLOW_QUERY_COUNT.fetch_add(1, Ordering::Relaxed);
+    // Parameters are bound separately, so the SQL text never contains imported values
+    let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    let params = params.iter()
+        .enumerate()
+        .map(|(i, param)| format!("${}={}", i + 1, param))
+        .collect::<Vec<_>>()
+        .join(", ");
+    log::warn!("Slow query during {} took {}ms (threshold {}ms): {} [{}]",
+        operation, elapsed.as_millis(), threshold_ms, sql, params);
+}
+
 async fn get_or_create_subscription(
     pool: &PgPool,
     name: &str,
+    azure_subscription_id: Option<&str>,
     cache: &mut HashMap<String, i64>,
 ) -> Result<i64> {
-    if let Some(&id) = cache.get(name) {
-        log::debug!("Found subscription '{}' in cache with ID: {}", name, id);
+    // The GUID identifies a subscription uniquely; display names can repeat across tenants
+    let cache_key = azure_subscription_id.unwrap_or(name);
+    if let Some(&id) = cache.get(cache_key) {
+        log::debug!("Found subscription '{}' in cache with ID: {}", cache_key, id);
         return Ok(id);
     }
-    log::debug!("Subscription '{}' not in cache, checking database", name);
+    log::debug!("Subscription '{}' not in cache, checking database", cache_key);
     
-    // Try to find existing subscription
-    if let Ok(row) = sqlx::query("SELECT id FROM subscription WHERE name = $1")
-        .bind(name)
-        .fetch_one(pool)
-        .await
-    {
+    // Try to find existing subscription by GUID
+    if let Some(guid) = azure_subscription_id {
+        let sql = "SELECT id FROM subscription WHERE azure_subscription_id = $1";
+        let row = with_retry("subscription lookup by GUID", sql, &[LogParam::Text(Some(guid))], || {
+            sqlx::query(sql)
+                .bind(guid)
+                .fetch_optional(pool)
+        })
+        .await?;
+        if let Some(row) = row {
+            let id: i64 = row.get("id");
+            log::debug!("Found existing subscription '{}' with ID: {}", guid, id);
+            cache.insert(cache_key.to_string(), id);
+            return Ok(id);
+        }
+    }
+    
+    // Fall back to the name, skipping rows that belong to a different GUID
+    let sql = "SELECT id FROM subscription
+        WHERE name = $1 AND (azure_subscription_id IS NULL OR $2::text IS NULL)
+        ORDER BY id LIMIT 1";
+    let row = with_retry("subscription lookup", sql, &[LogParam::Text(Some(name)), LogParam::Text(azure_subscription_id)], || {
+        sqlx::query(sql)
+            .bind(name)
+            .bind(azure_subscription_id)
+            .fetch_optional(pool)
+    })
+    .await?;
+    if let Some(row) = row {
         let id: i64 = row.get("id");
         log::debug!("Found existing subscription '{}' with ID: {}", name, id);
-        cache.insert(name.to_string(), id);
+        
+        if let Some(guid) = azure_subscription_id {
+            let sql = "UPDATE subscription SET azure_subscription_id = $2 WHERE id = $1";
+            with_retry("subscription GUID update", sql, &[LogParam::Id(id), LogParam::Text(Some(guid))], || {
+                sqlx::query(sql)
+                    .bind(id)
+                    .bind(guid)
+                    .execute(pool)
+            })
+            .await?;
+            log::info!("Recorded Azure subscription ID {} for subscription '{}'", guid, name);
+        }
+        
+        cache.insert(cache_key.to_string(), id);
         return Ok(id);
     }
-    log::debug!("Subscription '{}' not found, creating new one", name);
+    log::debug!("Subscription '{}' not found, creating new one", cache_key);
     
     // Create new subscription
-    let row = sqlx::query("INSERT INTO subscription (name) VALUES ($1) RETURNING id")
-        .bind(name)
-        .fetch_one(pool)
-        .await?;
+    let sql = "INSERT INTO subscription (name, azure_subscription_id) VALUES ($1, $2) RETURNING id";
+    let row = with_retry_policy("subscription insert", sql, &[LogParam::Text(Some(name)), LogParam::Text(azure_subscription_id)], false, || {
+        sqlx::query(sql)
+            .bind(name)
+            .bind(azure_subscription_id)
+            .fetch_one(pool)
+    })
+    .await?;
     
     let id: i64 = row.get("id");
     log::info!("Created new subscription '{}' with ID: {}", name, id);
-    cache.insert(name.to_string(), id);
+    cache.insert(cache_key.to_string(), id);
     Ok(id)
 }
 
@@ -272,7 +1476,8 @@ async fn get_or_create_resource_group(
     subscription_id: i64,
     cache: &mut HashMap<(String, i64), i64>,
 ) -> Result<i64> {
-    let key = (name.to_string(), subscription_id);
+    // Resource group names are case-insensitive in Azure, as in the ARM IDs that contain them
+    let key = (name.to_lowercase(), subscription_id);
     
     if let Some(&id) = cache.get(&key) {
         log::debug!("Found resource group '{}' in cache with ID: {}", name, id);
@@ -281,14 +1486,15 @@ async fn get_or_create_resource_group(
     log::debug!("Resource group '{}' not in cache, checking database", name);
     
     // Try to find existing resource group
-    if let Ok(row) = sqlx::query(
-        "SELECT id FROM resource_group WHERE name = $1 AND subscription_id = $2"
-    )
-    .bind(name)
-    .bind(subscription_id)
-    .fetch_one(pool)
-    .await
-    {
+    let sql = "SELECT id FROM resource_group WHERE lower(name) = lower($1) AND subscription_id = $2 ORDER BY id LIMIT 1";
+    let row = with_retry("resource group lookup", sql, &[LogParam::Text(Some(name)), LogParam::Id(subscription_id)], || {
+        sqlx::query(sql)
+            .bind(name)
+            .bind(subscription_id)
+            .fetch_optional(pool)
+    })
+    .await?;
+    if let Some(row) = row {
         let id: i64 = row.get("id");
         log::debug!("Found existing resource group '{}' with ID: {}", name, id);
         cache.insert(key, id);
@@ -297,12 +1503,13 @@ async fn get_or_create_resource_group(
     log::debug!("Resource group '{}' not found, creating new one", name);
     
     // Create new resource group
-    let row = sqlx::query(
-        "INSERT INTO resource_group (name, subscription_id) VALUES ($1, $2) RETURNING id"
-    )
-    .bind(name)
-    .bind(subscription_id)
-    .fetch_one(pool)
+    let sql = "INSERT INTO resource_group (name, subscription_id) VALUES ($1, $2) RETURNING id";
+    let row = with_retry_policy("resource group insert", sql, &[LogParam::Text(Some(name)), LogParam::Id(subscription_id)], false, || {
+        sqlx::query(sql)
+            .bind(name)
+            .bind(subscription_id)
+            .fetch_one(pool)
+    })
     .await?;
     
     let id: i64 = row.get("id");
@@ -317,6 +1524,10 @@ async fn get_or_create_application(
     parsed_tags: &ParsedTags,
     cache: &mut HashMap<String, i64>,
 ) -> Result<i64> {
+    // AppID tags are typed by hand; 'app01' and 'APP01 ' are the same application
+    let app_id = app_id.trim().to_uppercase();
+    let app_id = app_id.as_str();
+    
     if let Some(&id) = cache.get(app_id) {
         log::debug!("Found application '{}' in cache with ID: {}", app_id, id);
         return Ok(id);
@@ -324,11 +1535,15 @@ async fn get_or_create_application(
     log::debug!("Application '{}' not in cache, checking database", app_id);
     
     // Try to find existing application
-    if let Ok(row) = sqlx::query("SELECT id FROM application WHERE code = $1")
-        .bind(app_id)
-        .fetch_one(pool)
-        .await
-    {
+    // Migration 0005 normalizes stored codes; matching on UPPER(code) uses application_code_upper_key
+    let sql = "SELECT id FROM application WHERE UPPER(code) = $1";
+    let row = with_retry("application lookup", sql, &[LogParam::Text(Some(app_id))], || {
+        sqlx::query(sql)
+            .bind(app_id)
+            .fetch_optional(pool)
+    })
+    .await?;
+    if let Some(row) = row {
         let id: i64 = row.get("id");
         log::debug!("Found existing application '{}' with ID: {}", app_id, id);
         cache.insert(app_id.to_string(), id);
@@ -339,18 +1554,31 @@ async fn get_or_create_application(
     // Create new application
     let owner_email = parsed_tags.tags.get("AdminName")
         .or(parsed_tags.tags.get("AdminName1"))
-        .or(parsed_tags.tags.get("AdminName2"));
+        .or(parsed_tags.tags.get("AdminName2"))
+        .filter(|email| {
+            let valid = is_valid_email(email);
+            if !valid {
+                log::warn!("Ignoring invalid owner email '{}' for application '{}'", email, app_id);
+            }
+            valid
+        });
     
     let app_name = parsed_tags.tags.get("AppName");
     log::debug!("Creating application - Code: {}, Name: {:?}, Owner: {:?}", app_id, app_name, owner_email);
     
-    let row = sqlx::query(
-        "INSERT INTO application (code, name, owner_email) VALUES ($1, $2, $3) RETURNING id"
-    )
-    .bind(app_id)
-    .bind(app_name)
-    .bind(owner_email)
-    .fetch_one(pool)
+    let sql = "INSERT INTO application (code, name, owner_email) VALUES ($1, $2, $3) RETURNING id";
+    let params = &[
+        LogParam::Text(Some(app_id)),
+        LogParam::Text(app_name.map(String::as_str)),
+        LogParam::Text(owner_email.map(String::as_str)),
+    ];
+    let row = with_retry_policy("application insert", sql, params, false, || {
+        sqlx::query(sql)
+            .bind(app_id)
+            .bind(app_name)
+            .bind(owner_email)
+            .fetch_one(pool)
+    })
     .await?;
     
     let id: i64 = row.get("id");
@@ -365,7 +1593,7 @@ async fn insert_resource(
     parsed_tags: &ParsedTags,
     subscription_id: i64,
     resource_group_id: i64,
-) -> Result<i64> {
+) -> Result<(i64, bool)> {
     log::debug!("Preparing to insert resource: {} (type: {}, location: {})", 
         record.name, record.resource_type, record.location);
     
@@ -382,38 +1610,107 @@ async fn insert_resource(
     };
     
     let vendor = parsed_tags.tags.get("Vendor");
-    let environment = parsed_tags.tags.get("Environment");
+    let environment = parsed_tags.tags.get("Environment").map(|raw| {
+        let normalized = Environment::from_tag(raw);
+        if normalized == Environment::Unknown {
+            log::warn!("Unrecognized Environment tag '{}' on resource {}, storing as {}",
+                raw, record.name, normalized.as_str());
+        }
+        normalized.as_str()
+    });
     let provisioner = parsed_tags.tags.get("Provisioner");
     
     log::debug!("Resource metadata - Vendor: {:?}, Environment: {:?}, Provisioner: {:?}", 
         vendor, environment, provisioner);
     
-    let row = sqlx::query(
-        r#"
+    // Validation has already rejected unparseable IDs
+    if let Some(id) = record.azure_resource_id().ok().flatten() {
+        log::debug!("Resource ID parsed - ARM type: {}, resource group: {}", id.resource_type(), id.resource_group);
+    }
+    let azure_id = record.stored_azure_id();
+    
+    // Re-importing a resource with a known ARM ID updates it in place
+    let sql = r#"
         INSERT INTO resource (
             name, type, kind, location, subscription_id, resource_group_id,
-            tags_json, extended_location, vendor, environment, provisioner
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-        RETURNING id
-        "#
-    )
-    .bind(&record.name)
-    .bind(&record.resource_type)
-    .bind(kind)
-    .bind(&record.location)
-    .bind(subscription_id)
-    .bind(resource_group_id)
-    .bind(&parsed_tags.tags_json)
-    .bind(extended_location)
-    .bind(vendor)
-    .bind(environment)
-    .bind(provisioner)
-    .fetch_one(pool)
+            tags_json, extended_location, vendor, environment, provisioner, azure_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        ON CONFLICT (azure_id) DO UPDATE SET
+            name = EXCLUDED.name,
+            type = EXCLUDED.type,
+            kind = EXCLUDED.kind,
+            location = EXCLUDED.location,
+            subscription_id = EXCLUDED.subscription_id,
+            resource_group_id = EXCLUDED.resource_group_id,
+            tags_json = EXCLUDED.tags_json,
+            extended_location = EXCLUDED.extended_location,
+            vendor = EXCLUDED.vendor,
+            environment = EXCLUDED.environment,
+            provisioner = EXCLUDED.provisioner,
+            updated_at = NOW()
+        RETURNING id, (xmax = 0) AS inserted
+    "#;
+    let params = &[
+        LogParam::Text(Some(&record.name)),
+        LogParam::Text(Some(&record.resource_type)),
+        LogParam::Text(kind),
+        LogParam::Text(Some(&record.location)),
+        LogParam::Id(subscription_id),
+        LogParam::Id(resource_group_id),
+        LogParam::Json(&parsed_tags.tags_json),
+        LogParam::Text(extended_location),
+        LogParam::Text(vendor.map(String::as_str)),
+        LogParam::Text(environment),
+        LogParam::Text(provisioner.map(String::as_str)),
+        LogParam::Text(azure_id.as_deref()),
+    ];
+    // Without an ARM ID there is no conflict target, so the insert is not idempotent
+    let row = with_retry_policy("resource insert", sql, params, azure_id.is_some(), || {
+        sqlx::query(sql)
+            .bind(&record.name)
+            .bind(&record.resource_type)
+            .bind(kind)
+            .bind(&record.location)
+            .bind(subscription_id)
+            .bind(resource_group_id)
+            .bind(&parsed_tags.tags_json)
+            .bind(extended_location)
+            .bind(vendor)
+            .bind(environment)
+            .bind(provisioner)
+            .bind(azure_id.as_deref())
+            .fetch_one(pool)
+    })
     .await?;
     
     let resource_id = row.get("id");
-    log::debug!("Resource '{}' inserted successfully with ID: {}", record.name, resource_id);
-    Ok(resource_id)
+    let inserted: bool = row.get("inserted");
+    log::debug!("Resource '{}' {} with ID: {}", record.name,
+        if inserted { "inserted" } else { "updated" }, resource_id);
+    Ok((resource_id, inserted))
+}
+
+// Drop tags and importer-created application links so removed tags and changed AppIDs don't linger
+async fn clear_resource_tags_and_links(pool: &PgPool, resource_id: i64) -> Result<()> {
+    let sql = "DELETE FROM resource_tag WHERE resource_id = $1";
+    let tags = with_retry("resource tag delete", sql, &[LogParam::Id(resource_id)], || {
+        sqlx::query(sql)
+            .bind(resource_id)
+            .execute(pool)
+    })
+    .await?;
+    
+    let sql = "DELETE FROM resource_application_map WHERE resource_id = $1 AND relation_type = 'uses'";
+    let links = with_retry("resource-application link delete", sql, &[LogParam::Id(resource_id)], || {
+        sqlx::query(sql)
+            .bind(resource_id)
+            .execute(pool)
+    })
+    .await?;
+    
+    log::debug!("Cleared {} tags and {} application links for updated resource {}",
+        tags.rows_affected(), links.rows_affected(), resource_id);
+    Ok(())
 }
 
 async fn insert_resource_tags(
@@ -424,14 +1721,15 @@ async fn insert_resource_tags(
     let mut tag_count = 0;
     for (key, value) in &parsed_tags.tags {
         log::debug!("Inserting tag for resource {}: {} = {}", resource_id, key, value);
-        match sqlx::query(
-            "INSERT INTO resource_tag (resource_id, key, value) VALUES ($1, $2, $3)
-             ON CONFLICT (resource_id, key) DO UPDATE SET value = EXCLUDED.value"
-        )
-        .bind(resource_id)
-        .bind(key)
-        .bind(Some(value))
-        .execute(pool)
+        let sql = "INSERT INTO resource_tag (resource_id, key, value) VALUES ($1, $2, $3)
+            ON CONFLICT (resource_id, key) DO UPDATE SET value = EXCLUDED.value";
+        match with_retry("resource tag insert", sql, &[LogParam::Id(resource_id), LogParam::Text(Some(key)), LogParam::Text(Some(value))], || {
+            sqlx::query(sql)
+                .bind(resource_id)
+                .bind(key)
+                .bind(Some(value))
+                .execute(pool)
+        })
         .await {
             Ok(_) => {
                 tag_count += 1;
@@ -447,6 +1745,30 @@ async fn insert_resource_tags(
     Ok(())
 }
 
+async fn link_child_resources(pool: &PgPool, pending: &[(i64, String)]) -> Result<u64> {
+    let child_ids: Vec<i64> = pending.iter().map(|(id, _)| *id).collect();
+    let parent_azure_ids: Vec<&str> = pending.iter().map(|(_, parent)| parent.as_str()).collect();
+    log::debug!("Resolving parents for {} child resources", child_ids.len());
+    
+    // Stored ARM IDs are lowercase
+    let sql = r#"
+        INSERT INTO resource_relationship (parent_id, child_id, relation_type)
+        SELECT p.id, c.child_id, 'parent'
+        FROM unnest($1::bigint[], $2::text[]) AS c(child_id, parent_azure_id)
+        JOIN resource p ON p.azure_id = lower(c.parent_azure_id)
+        ON CONFLICT (parent_id, child_id) DO NOTHING
+    "#;
+    let result = with_retry("resource relationship insert", sql, &[LogParam::Array(child_ids.len()), LogParam::Array(parent_azure_ids.len())], || {
+        sqlx::query(sql)
+            .bind(&child_ids)
+            .bind(&parent_azure_ids)
+            .execute(pool)
+    })
+    .await?;
+    
+    Ok(result.rows_affected())
+}
+
 async fn link_resource_to_application(
     pool: &PgPool,
     resource_id: i64,
@@ -454,17 +1776,17 @@ async fn link_resource_to_application(
 ) -> Result<()> {
     log::debug!("Creating resource-application link: resource {} -> application {}", resource_id, application_id);
     
-    match sqlx::query(
-        r#"
+    let sql = r#"
         INSERT INTO resource_application_map (resource_id, application_id, relation_type)
         VALUES ($1, $2, 'uses')
         ON CONFLICT (resource_id, application_id, relation_type) DO NOTHING
-        "#
-    )
-    .bind(resource_id)
-    .bind(application_id)
-    .bind("uses")
-    .execute(pool)
+    "#;
+    match with_retry("resource-application link", sql, &[LogParam::Id(resource_id), LogParam::Id(application_id)], || {
+        sqlx::query(sql)
+            .bind(resource_id)
+            .bind(application_id)
+            .execute(pool)
+    })
     .await {
         Ok(_) => {
             log::debug!("Resource-application link created successfully");
@@ -476,4 +1798,337 @@ async fn link_resource_to_application(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUID: &str = "11111111-2222-3333-4444-555555555555";
+
+    fn record() -> CsvRecord {
+        CsvRecord {
+            name: "vm1".to_string(),
+            resource_type: "Virtual machine".to_string(),
+            kind: None,
+            location: "southeastasia".to_string(),
+            subscription: "Sub-Prod".to_string(),
+            resource_group: "rg1".to_string(),
+            tags: "null".to_string(),
+            extended_location: None,
+            subscription_id: Some(GUID.to_string()),
+            azure_id: Some(format!(
+                "/subscriptions/{}/resourceGroups/rg1/providers/Microsoft.Compute/virtualMachines/vm1",
+                GUID
+            )),
+        }
+    }
+
+    #[test]
+    fn log_format_parsing() {
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert!("jsn".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn schema_check_parsing() {
+        assert_eq!("strict".parse(), Ok(SchemaCheck::Strict));
+        assert_eq!("WARN".parse(), Ok(SchemaCheck::Warn));
+        assert_eq!("off".parse(), Ok(SchemaCheck::Off));
+        assert!("wran".parse::<SchemaCheck>().is_err());
+        assert!("".parse::<SchemaCheck>().is_err());
+    }
+
+    #[test]
+    fn validate_accepts_complete_record() {
+        assert_eq!(record().validate(), Ok(()));
+
+        let mut without_id = record();
+        without_id.azure_id = None;
+        without_id.subscription_id = None;
+        assert_eq!(without_id.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_empty_required_fields() {
+        let mut r = record();
+        r.name = "  ".to_string();
+        assert!(r.validate().is_err());
+
+        let mut r = record();
+        r.resource_type = String::new();
+        assert!(r.validate().is_err());
+
+        let mut r = record();
+        r.subscription = String::new();
+        assert!(r.validate().is_err());
+
+        let mut r = record();
+        r.resource_group = String::new();
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_location() {
+        let mut r = record();
+        r.location = "south/east".to_string();
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_azure_id_that_disagrees_with_columns() {
+        let mut r = record();
+        r.resource_group = "rg2".to_string();
+        assert!(r.validate().unwrap_err().contains("resource group"));
+
+        let mut r = record();
+        r.name = "vm2".to_string();
+        assert!(r.validate().unwrap_err().contains("name"));
+
+        let mut r = record();
+        r.subscription_id = Some("99999999-2222-3333-4444-555555555555".to_string());
+        assert!(r.validate().unwrap_err().contains("subscription"));
+
+        let mut r = record();
+        r.azure_id = Some("/subscriptions/not-a-guid".to_string());
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn validate_compares_azure_id_case_insensitively() {
+        let mut r = record();
+        r.resource_group = "RG1".to_string();
+        r.name = "VM1".to_string();
+        r.subscription_id = Some(GUID.to_uppercase());
+        assert_eq!(r.validate(), Ok(()));
+    }
+
+    #[test]
+    fn location_validation() {
+        assert!(is_valid_location("southeastasia"));
+        assert!(is_valid_location("Southeast Asia"));
+        assert!(is_valid_location("us-gov-virginia"));
+        assert!(!is_valid_location(""));
+        assert!(!is_valid_location("   "));
+        assert!(!is_valid_location("east_us"));
+        assert!(!is_valid_location("east/us"));
+    }
+
+    #[test]
+    fn tag_key_validation() {
+        assert!(is_valid_tag_key("Environment"));
+        assert!(is_valid_tag_key("cost-center.team"));
+        assert!(!is_valid_tag_key(""));
+        assert!(!is_valid_tag_key(" "));
+        for forbidden in ["a<b", "a>b", "a%b", "a&b", "a\\b", "a?b", "a/b"] {
+            assert!(!is_valid_tag_key(forbidden), "{} should be rejected", forbidden);
+        }
+    }
+
+    #[test]
+    fn tag_key_limit_counts_characters() {
+        // 512 two-byte characters is within Azure's 512-character limit
+        assert!(is_valid_tag_key(&"é".repeat(512)));
+        assert!(!is_valid_tag_key(&"é".repeat(513)));
+        assert!(!is_valid_tag_key(&"a".repeat(513)));
+    }
+
+    #[test]
+    fn email_validation() {
+        assert!(is_valid_email("owner@example.com"));
+        assert!(is_valid_email("first.last@sub.example.co.th"));
+        assert!(!is_valid_email("owner"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("owner@example"));
+        assert!(!is_valid_email("owner@.example.com"));
+        assert!(!is_valid_email("owner@example.com."));
+        assert!(!is_valid_email("a@b@example.com"));
+        assert!(!is_valid_email("owner name@example.com"));
+    }
+
+    fn vm_id() -> String {
+        format!("/subscriptions/{}/resourceGroups/rg1/providers/Microsoft.Compute/virtualMachines/vm1", GUID)
+    }
+
+    #[test]
+    fn azure_id_round_trips() {
+        let id: AzureResourceId = vm_id().parse().unwrap();
+        assert_eq!(id.subscription_id, GUID);
+        assert_eq!(id.resource_group, "rg1");
+        assert_eq!(id.resource_type(), "Microsoft.Compute/virtualMachines");
+        assert_eq!(id.name(), "vm1");
+        assert_eq!(id.parent(), None);
+        assert_eq!(id.to_string(), vm_id());
+        // Surrounding whitespace and a trailing slash are tolerated
+        assert_eq!(format!(" {}/ ", vm_id()).parse::<AzureResourceId>().unwrap(), id);
+    }
+
+    #[test]
+    fn azure_id_parses_child_resources() {
+        let subnet = format!(
+            "/subscriptions/{}/resourceGroups/rg1/providers/Microsoft.Network/virtualNetworks/vnet1/subnets/subnet1",
+            GUID
+        );
+        let id: AzureResourceId = subnet.parse().unwrap();
+        assert_eq!(id.resource_type(), "Microsoft.Network/virtualNetworks/subnets");
+        assert_eq!(id.name(), "subnet1");
+        assert_eq!(id.to_string(), subnet);
+    }
+
+    #[test]
+    fn azure_id_derives_parents() {
+        let base = format!("/subscriptions/{}/resourceGroups/rg1/providers/Microsoft.Sql/servers/sql1", GUID);
+        let id: AzureResourceId = format!("{}/databases/db1/backupShortTermRetentionPolicies/default", base)
+            .parse()
+            .unwrap();
+
+        let parent = id.parent().unwrap();
+        assert_eq!(parent.to_string(), format!("{}/databases/db1", base));
+        assert_eq!(parent.resource_type(), "Microsoft.Sql/servers/databases");
+
+        let grandparent = parent.parent().unwrap();
+        assert_eq!(grandparent.to_string(), base);
+        assert_eq!(grandparent.parent(), None);
+    }
+
+    #[test]
+    fn azure_id_accepts_keywords_in_any_case() {
+        let id: AzureResourceId = format!(
+            "/SUBSCRIPTIONS/{}/resourcegroups/RG1/PROVIDERS/Microsoft.Compute/virtualMachines/VM1",
+            GUID.to_uppercase()
+        )
+        .parse()
+        .unwrap();
+        // Keywords are written canonically and the GUID lowercased; names keep their case
+        assert_eq!(
+            id.to_string(),
+            format!("/subscriptions/{}/resourceGroups/RG1/providers/Microsoft.Compute/virtualMachines/VM1", GUID)
+        );
+    }
+
+    #[test]
+    fn azure_id_rejects_odd_segment_counts() {
+        assert!(format!("{}/extensions", vm_id()).parse::<AzureResourceId>().is_err());
+        assert!(format!("{}/extensions/", vm_id()).parse::<AzureResourceId>().is_err());
+        assert!(format!("/subscriptions/{}/resourceGroups/rg1/providers/Microsoft.Compute", GUID)
+            .parse::<AzureResourceId>()
+            .is_err());
+    }
+
+    #[test]
+    fn azure_id_rejects_bad_guid_and_structure() {
+        let bad_guid = vm_id().replace(GUID, "11111111-2222-3333-4444-55555555555z");
+        assert!(bad_guid.parse::<AzureResourceId>().unwrap_err().contains("subscription GUID"));
+        assert!(vm_id().replace(GUID, "not-a-guid").parse::<AzureResourceId>().is_err());
+        assert!(vm_id().replace("resourceGroups", "groups").parse::<AzureResourceId>().is_err());
+        assert!(vm_id().replace("providers", "provider").parse::<AzureResourceId>().is_err());
+        assert!("".parse::<AzureResourceId>().is_err());
+    }
+
+    #[test]
+    fn extension_ids_are_recognized_but_not_parsed() {
+        let extension = format!("{}/providers/Microsoft.Insights/diagnosticSettings/ds1", vm_id());
+        assert!(is_extension_resource_id(&extension));
+        assert!(extension.parse::<AzureResourceId>().is_err());
+
+        let extension_upper = extension.to_uppercase();
+        let mut r = record();
+        r.name = "ds1".to_string();
+        r.azure_id = Some(extension.clone());
+        assert_eq!(r.validate(), Ok(()));
+        assert_eq!(r.azure_resource_id(), Ok(None));
+        // Still stored, so the upsert matches it on re-import
+        r.azure_id = Some(format!(" {}/ ", extension_upper));
+        assert_eq!(r.stored_azure_id(), Some(extension.to_lowercase()));
+
+        assert!(!is_extension_resource_id(&vm_id()));
+        // Incomplete extension, or an extension on a malformed base ID
+        assert!(!is_extension_resource_id(&format!("{}/providers/Microsoft.Insights", vm_id())));
+        assert!(!is_extension_resource_id(
+            &format!("{}/providers/Microsoft.Insights/diagnosticSettings/ds1", vm_id()).replace(GUID, "nope")
+        ));
+    }
+
+    #[test]
+    fn environment_aliases_match_normalize_migration() {
+        // Migration 0004 repeats the alias table in SQL; both must map every alias the same way
+        let migration = include_str!("../../migrations/0004_normalize_environment.sql");
+        let mut checked = 0;
+        for line in migration.lines() {
+            let line = line.trim();
+            let Some(rest) = line.strip_prefix("WHEN '") else {
+                continue;
+            };
+            let (alias, rest) = rest.split_once('\'').unwrap();
+            let expected = rest.split('\'').nth(1).unwrap();
+            assert_eq!(Environment::from_tag(alias).as_str(), expected, "alias '{}'", alias);
+            checked += 1;
+        }
+        assert_eq!(checked, 18, "unexpected number of aliases in migration 0004");
+        assert!(migration.contains("ELSE 'UNKNOWN'"));
+    }
+
+    #[test]
+    fn environment_normalization() {
+        assert_eq!(Environment::from_tag(" Production "), Environment::Prod);
+        assert_eq!(Environment::from_tag("PRD"), Environment::Prod);
+        assert_eq!(Environment::from_tag("Pre-Prod"), Environment::Uat);
+        assert_eq!(Environment::from_tag("SIT"), Environment::Test);
+        assert_eq!(Environment::from_tag("Develop"), Environment::Dev);
+        assert_eq!(Environment::from_tag("sandbox"), Environment::Unknown);
+        assert_eq!(Environment::from_tag(""), Environment::Unknown);
+        // Stored values normalize to themselves
+        for env in [Environment::Dev, Environment::Test, Environment::Uat, Environment::Prod, Environment::Unknown] {
+            assert_eq!(Environment::from_tag(env.as_str()), env);
+        }
+    }
+
+    #[test]
+    fn parse_tags_drops_invalid_keys_from_both_stores() {
+        let parsed = parse_tags(r#"{"AppID":"AP01","bad/key":"x","Env":null}"#).unwrap();
+        assert_eq!(parsed.tags.get("AppID").map(String::as_str), Some("AP01"));
+        assert!(!parsed.tags.contains_key("bad/key"));
+        assert!(parsed.tags_json.get("bad/key").is_none());
+        assert!(parsed.tags_json.get("AppID").is_some());
+    }
+
+    #[test]
+    fn log_params_hide_imported_values() {
+        let tags = serde_json::json!({"AppID": "AP1001", "Owner": "someone@example.com"});
+        let shown = [
+            LogParam::Id(42),
+            LogParam::Text(Some("someone@example.com")),
+            LogParam::Text(None),
+            LogParam::Json(&tags),
+            LogParam::Array(3),
+        ]
+        .map(|param| param.to_string());
+        assert_eq!(shown, ["42", "<text, 19 chars>", "NULL", "<jsonb, 2 keys>", "<array, 3 items>"]);
+    }
+
+    fn config_source(toml: &str) -> ConfigSource {
+        ConfigSource { path: None, file: toml.parse().unwrap() }
+    }
+
+    #[test]
+    fn config_errors_name_the_file_key() {
+        let source = config_source("[database]\nrun_migrations = \"sometimes\"");
+        let err = source.get_bool("TECHSTOCK_TEST_UNSET_BOOL", "database.run_migrations").unwrap_err();
+        assert!(err.to_string().contains("database.run_migrations"), "{}", err);
+
+        let source = config_source("[database]\nrun_migrations = true");
+        assert_eq!(source.get_bool("TECHSTOCK_TEST_UNSET_BOOL", "database.run_migrations").unwrap(), Some(true));
+    }
+
+    #[test]
+    fn config_lookup_accepts_string_arrays_only() {
+        let source = config_source("[notifications]\nevents = [\"import_completed\", \"import_failed\"]");
+        assert_eq!(source.lookup("notifications.events").unwrap().as_deref(), Some("import_completed,import_failed"));
+        assert_eq!(source.lookup("notifications.missing").unwrap(), None);
+
+        let source = config_source("[notifications]\nevents = [1, 2]");
+        assert!(source.lookup("notifications.events").is_err());
+        assert!(source.lookup("notifications").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Fixtures for the database-backed tests.
+//!
+//! Each test gets its own database. Set `TEST_DATABASE_URL` to a disposable server (e.g. the one
+//! `run-postgres.sh` starts); otherwise a throwaway cluster is started with `initdb` and `pg_ctl`.
+//! When neither is available the tests are skipped.
+
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::ConnectOptions;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+pub const SUBSCRIPTION_GUID: &str = "11111111-2222-3333-4444-555555555555";
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A Postgres cluster started for a single test, stopped when dropped.
+struct Cluster {
+    data_dir: PathBuf,
+}
+
+impl Cluster {
+    fn start(dir: &Path) -> Option<Self> {
+        let data_dir = dir.join("data");
+        let initdb = Command::new("initdb")
+            .arg("-D").arg(&data_dir)
+            .args(["-U", "postgres", "-A", "trust", "--no-sync"])
+            .output()
+            .ok()?;
+        if !initdb.status.success() {
+            eprintln!("initdb failed: {}", String::from_utf8_lossy(&initdb.stderr).trim());
+            return None;
+        }
+
+        // Listen only on a socket inside the test directory, so parallel tests don't share a port
+        let cluster = Self { data_dir };
+        let started = Command::new("pg_ctl")
+            .args(["start", "-w", "-D"]).arg(&cluster.data_dir)
+            .arg("-l").arg(dir.join("postgres.log"))
+            .arg("-o").arg(format!("-k {} -c listen_addresses=''", dir.display()))
+            .output()
+            .ok()?;
+        if !started.status.success() {
+            eprintln!("pg_ctl start failed: {}", String::from_utf8_lossy(&started.stderr).trim());
+            return None;
+        }
+        Some(cluster)
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        let _ = Command::new("pg_ctl")
+            .args(["stop", "-m", "immediate", "-D"]).arg(&self.data_dir)
+            .output();
+    }
+}
+
+/// A fresh, empty database plus a scratch directory for CSV files.
+pub struct TestDb {
+    pub pool: PgPool,
+    url: String,
+    admin_url: String,
+    name: String,
+    dir: PathBuf,
+    cluster: Option<Cluster>,
+}
+
+impl TestDb {
+    /// Creates the database, or returns `None` (and the test should return) when no server is available.
+    pub async fn create() -> Option<Self> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let name = format!("techstock_test_{}_{}", std::process::id(), id);
+        let dir = std::env::temp_dir().join(&name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test directory");
+
+        let (admin_url, cluster) = match std::env::var("TEST_DATABASE_URL") {
+            Ok(url) => (url, None),
+            Err(_) => match Cluster::start(&dir) {
+                Some(cluster) => (format!("postgres://postgres@localhost/postgres?host={}", dir.display()), Some(cluster)),
+                None => {
+                    eprintln!("Skipping database test: set TEST_DATABASE_URL or put initdb and pg_ctl on PATH");
+                    let _ = std::fs::remove_dir_all(&dir);
+                    return None;
+                }
+            },
+        };
+
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&admin_url)
+            .await
+            .expect("connect to test server");
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&admin)
+            .await
+            .expect("create test database");
+        admin.close().await;
+
+        let options = PgConnectOptions::from_str(&admin_url)
+            .expect("parse test server URL")
+            .database(&name);
+        let url = options.to_url_lossy().to_string();
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .expect("connect to test database");
+        Some(Self { pool, url, admin_url, name, dir, cluster })
+    }
+
+    /// Applies the embedded migrations up to and including `version`, as an older release would have.
+    pub async fn migrate_to(&self, version: i64) {
+        let mut conn = self.pool.acquire().await.expect("acquire connection");
+        conn.ensure_migrations_table().await.expect("create migrations table");
+        for migration in MIGRATOR.iter().filter(|m| m.version <= version) {
+            conn.apply(migration).await.expect("apply migration");
+        }
+    }
+
+    /// Writes `csv` to the scratch directory and runs the importer binary against this database.
+    pub fn import(&self, csv: &str, run_migrations: bool) -> Output {
+        let csv_path = self.dir.join("import.csv");
+        std::fs::write(&csv_path, csv).expect("write CSV");
+
+        // A clean environment and working directory, so no local .env or techstock.toml leaks in
+        Command::new(env!("CARGO_BIN_EXE_import"))
+            .current_dir(&self.dir)
+            .env_clear()
+            .env("DATABASE_URL", &self.url)
+            .env("CSV_PATH", &csv_path)
+            .env("RUN_MIGRATIONS", run_migrations.to_string())
+            .env("DB_CONNECT_RETRIES", "0")
+            .env("RUST_LOG", "warn")
+            .output()
+            .expect("run importer")
+    }
+
+    /// Like `import`, but fails the test when the importer does.
+    pub fn import_ok(&self, csv: &str) {
+        let output = self.import(csv, true);
+        assert!(output.status.success(), "import failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    pub async fn count(&self, sql: &str) -> i64 {
+        sqlx::query_scalar(sql)
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or_else(|e| panic!("{}: {}", sql, e))
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        // A throwaway cluster goes away with its directory; on a shared server, drop the database.
+        // Drop can't await, so use a short-lived runtime on its own thread.
+        if self.cluster.is_none() {
+            let admin_url = self.admin_url.clone();
+            let name = self.name.clone();
+            let _ = std::thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+                runtime.block_on(async {
+                    let admin = PgPoolOptions::new().max_connections(1).connect(&admin_url).await?;
+                    sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+                        .execute(&admin)
+                        .await?;
+                    admin.close().await;
+                    Ok::<_, sqlx::Error>(())
+                })
+                .map_err(std::io::Error::other)
+            })
+            .join();
+        }
+        self.cluster.take();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Builds a Resource Graph CSV with the header the importer expects.
+/// Rows are (name, type, resource group, tags JSON, ARM ID).
+pub fn csv(rows: &[(&str, &str, &str, &str, &str)]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "Name", "Type", "kind", "Location", "Subscription", "Resource group",
+        "Tags", "extendedLocation", "subscriptionId", "id",
+    ]).unwrap();
+    for &(name, resource_type, resource_group, tags, id) in rows {
+        writer.write_record([
+            name, resource_type, "", "southeastasia", "sub-test", resource_group,
+            tags, "null", SUBSCRIPTION_GUID, id,
+        ]).unwrap();
+    }
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+/// ARM ID of a resource in the test subscription, e.g. `arm_id("rg1", "Microsoft.Compute/virtualMachines/vm1")`.
+pub fn arm_id(resource_group: &str, path: &str) -> String {
+    format!("/subscriptions/{}/resourceGroups/{}/providers/{}", SUBSCRIPTION_GUID, resource_group, path)
+}
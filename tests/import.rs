@@ -0,0 +1,126 @@
+//! End-to-end import tests against a real Postgres; see `common` for how the server is provided.
+
+mod common;
+
+use common::{TestDb, arm_id, csv};
+
+fn inventory() -> String {
+    let vnet = arm_id("rg1", "Microsoft.Network/virtualNetworks/vnet1");
+    let vm = arm_id("rg1", "Microsoft.Compute/virtualMachines/vm1");
+    csv(&[
+        ("vnet1", "Virtual network", "rg1", r#"{"AppID":"app01","Environment":"prd"}"#, &vnet),
+        ("subnet1", "Subnet", "rg1", "null", &format!("{}/subnets/subnet1", vnet)),
+        ("vm1", "Virtual machine", "rg1", r#"{"AppID":"APP01","Owner":"team-a"}"#, &vm),
+        ("ds1", "Diagnostic setting", "rg1", "null",
+            &format!("{}/providers/Microsoft.Insights/diagnosticSettings/ds1", vm)),
+    ])
+}
+
+async fn snapshot(db: &TestDb) -> Vec<(i64, String, i64)> {
+    sqlx::query_as("SELECT id, azure_id, resource_group_id FROM resource ORDER BY id")
+        .fetch_all(&db.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn reimport_is_idempotent() {
+    let Some(db) = TestDb::create().await else { return };
+
+    db.import_ok(&inventory());
+    let first = snapshot(&db).await;
+    let tags = db.count("SELECT COUNT(*) FROM resource_tag").await;
+    assert_eq!(first.len(), 4);
+
+    db.import_ok(&inventory());
+    db.import_ok(&inventory());
+    assert_eq!(snapshot(&db).await, first, "re-import must update resources in place");
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_tag").await, tags);
+    assert_eq!(db.count("SELECT COUNT(*) FROM subscription").await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_group").await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM application").await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_application_map").await, 2);
+    // subnet1 → vnet1; the extension resource is not linked to a parent
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_relationship").await, 1);
+}
+
+#[tokio::test]
+async fn reimport_replaces_tags_and_application_links() {
+    let Some(db) = TestDb::create().await else { return };
+    db.import_ok(&inventory());
+
+    // Same VM, written with a different resource group case, a new AppID and the Owner tag removed
+    let vm = arm_id("RG1", "Microsoft.Compute/virtualMachines/VM1");
+    db.import_ok(&csv(&[("vm1", "Virtual machine", "RG1", r#"{"AppID":"app02"}"#, &vm)]));
+
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource").await, 4);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_group").await, 1);
+
+    let tags: Vec<(String, String)> = sqlx::query_as(
+        "SELECT t.key, t.value FROM resource_tag t JOIN resource r ON r.id = t.resource_id
+         WHERE r.name = 'vm1' ORDER BY t.key",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(tags, [("AppID".to_string(), "app02".to_string())]);
+
+    let apps: Vec<String> = sqlx::query_scalar(
+        "SELECT a.code FROM resource_application_map m
+         JOIN resource r ON r.id = m.resource_id JOIN application a ON a.id = m.application_id
+         WHERE r.name = 'vm1'",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .unwrap();
+    assert_eq!(apps, ["APP02"]);
+}
+
+#[tokio::test]
+async fn migration_0005_merges_duplicate_applications() {
+    let Some(db) = TestDb::create().await else { return };
+    db.migrate_to(4).await;
+
+    // Applications created before AppID normalization, one resource linked to the duplicate
+    sqlx::raw_sql(
+        "INSERT INTO application (id, code, name, owner_email) VALUES
+             (1, 'APP01', 'App One', NULL),
+             (2, 'app01 ', NULL, 'owner@example.com'),
+             (3, 'APP09', 'Other', NULL);
+         SELECT setval('application_id_seq', 3);
+         INSERT INTO resource (id, name, type) VALUES (100, 'legacy-vm', 'Virtual machine');
+         INSERT INTO resource_application_map (resource_id, application_id) VALUES (100, 2);",
+    )
+    .execute(&db.pool)
+    .await
+    .unwrap();
+
+    db.import_ok(&inventory());
+
+    let apps: Vec<(i64, String, Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT id, code, name, owner_email FROM application ORDER BY id")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+    assert_eq!(apps, [
+        (1, "APP01".to_string(), Some("App One".to_string()), Some("owner@example.com".to_string())),
+        (3, "APP09".to_string(), Some("Other".to_string()), None),
+    ]);
+    // The legacy link moved to the kept row, and the import reused it
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_application_map WHERE application_id <> 1").await, 0);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_application_map WHERE resource_id = 100").await, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource_application_map").await, 3);
+}
+
+#[tokio::test]
+async fn schema_check_rejects_an_unmigrated_database() {
+    let Some(db) = TestDb::create().await else { return };
+    db.migrate_to(4).await;
+
+    let output = db.import(&inventory(), false);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("expected"), "{}", stderr);
+    assert!(stderr.contains("Database schema is out of date"), "{}", stderr);
+    assert_eq!(db.count("SELECT COUNT(*) FROM resource").await, 0);
+}